@@ -0,0 +1,208 @@
+//! Measures how much work propagation does when collapsing a square grid of
+//! bitset states, with the rule and with [SupportPropagator], and how much
+//! the propagation queue saves by holding each cell at most once. Run with
+//! `cargo bench --bench propagation`.
+
+use std::{collections::VecDeque, ops::{Index, IndexMut}, time::Instant};
+
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+use common::PropagateOnly;
+
+type S = BitsetState<11>;
+type Grid = SquareGrid<S>;
+
+const A: S = S::state(0);
+const B: S = S::state(1);
+const C: S = S::state(2);
+const D: S = S::state(3);
+const E: S = S::state(4);
+const F: S = S::state(5);
+const G: S = S::state(6);
+const H: S = S::state(7);
+const I: S = S::state(8);
+const J: S = S::state(9);
+const K: S = S::state(10);
+
+const UP: (isize, isize) = (0, -1);
+const DOWN: (isize, isize) = (0, 1);
+const LEFT: (isize, isize) = (-1, 0);
+const RIGHT: (isize, isize) = (1, 0);
+
+const SIZE: isize = 128;
+const RUNS: u64 = 10;
+
+// the rectangle tileset from examples/procedural_texture.rs
+fn rule<Sp: Space<S, Coordinate = (isize, isize), CoordinateDelta = (isize, isize)>>() -> SetCollapseRule<S, Sp, UniformSetCollapseObserver> {
+	SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&E, &[(UP, E | B), (LEFT, E | D), (RIGHT, E | F), (DOWN, E | H)])
+		.allow(&A, &[(LEFT, C | F | I), (UP, G | H | I)])
+		.allow(&B, &[(LEFT, A | B), (RIGHT, C | B), (UP, G | H | I)])
+		.allow(&C, &[(UP, G | H | I), (RIGHT, A | D | G)])
+		.allow(&G, &[(DOWN, A | B | C), (LEFT, C | F | I)])
+		.allow(&I, &[(RIGHT, A | D | G), (DOWN, A | B | C)])
+		.allow(&H, &[(LEFT, G | H), (RIGHT, I | H), (DOWN, A | B | C)])
+		.allow(&F, &[(UP, C | F), (DOWN, I | F), (RIGHT, A | D | C)])
+		.allow(&D, &[(UP, A | D), (DOWN, G | D), (LEFT, C | F | I)])
+		.allow(&J, &[(UP, J | G | H | I | K), (DOWN, J | A | B | C | K), (LEFT, J | C | F | I | K), (RIGHT, J | A | D | G | K)])
+		.build()
+}
+
+fn run(rule: &SetCollapseRule<S, Grid, UniformSetCollapseObserver>, support_counting: bool) {
+	let mut stats = CollapseStats::default();
+	let mut failures = 0;
+	let start = Instant::now();
+	for seed in 0..RUNS {
+		let mut grid = Grid::new(SIZE, SIZE, |_, _| S::all());
+		let mut rng = StdRng::seed_from_u64(seed);
+		let mut collapser = Collapser::new(&mut grid, rule).with_rng(&mut rng);
+		if support_counting {
+			collapser = collapser.with_propagator(SupportPropagator::new(rule));
+		}
+		if collapser.run().is_err() {
+			failures += 1;
+		}
+		stats += collapser.stats();
+	}
+	let elapsed = start.elapsed();
+	if support_counting {
+		println!("support counting propagation:");
+	} else {
+		println!("rule propagation:");
+	}
+	println!("  {} runs ({} failed)", RUNS, failures);
+	println!("  collapse() calls per run: {}", stats.rule_collapses as u64 / RUNS);
+	println!("  propagation steps per run: {}", stats.propagation_steps as u64 / RUNS);
+	println!("  peak queue length: {}", stats.peak_queue_length);
+	println!("  time per run: {:?}", elapsed / RUNS as u32);
+	println!("    selection: {:?}", stats.selection_time / RUNS as u32);
+	println!("    observation: {:?}", stats.observation_time / RUNS as u32);
+	println!("    propagation: {:?}", stats.propagation_time / RUNS as u32);
+}
+
+// Square grid without Space::index_of, so that the propagation queue falls
+// back to tracking cells in a hash set
+struct Unindexed(Grid);
+
+impl Index<(isize, isize)> for Unindexed {
+	type Output = S;
+	
+	fn index(&self, index: (isize, isize)) -> &S {
+		&self.0[index]
+	}
+}
+
+impl IndexMut<(isize, isize)> for Unindexed {
+	fn index_mut(&mut self, index: (isize, isize)) -> &mut S {
+		&mut self.0[index]
+	}
+}
+
+impl Space<S> for Unindexed {
+	type Coordinate = (isize, isize);
+	type CoordinateDelta = (isize, isize);
+	
+	fn coordinate_list(&self) -> Box<[(isize, isize)]> {
+		self.0.coordinate_list()
+	}
+	
+	fn neighbors(&self, coord: (isize, isize), neighbor_directions: &[(isize, isize)], neighbors: &mut [Option<(isize, isize)>]) {
+		self.0.neighbors(coord, neighbor_directions, neighbors);
+	}
+}
+
+// Sparse tiles to propagate out from. Each cell between them is reached from
+// several directions, so it is queued again while it's still waiting.
+fn seeded_grid() -> Grid {
+	Grid::new(SIZE, SIZE, |x, y| if x % 8 == 0 && y % 8 == 0 { E } else { S::all() })
+}
+
+// Propagation as it was done before the queue held each cell at most once,
+// returning the number of collapse() calls and the number of times a cell
+// was queued while already waiting
+fn propagate_with_plain_queue(grid: &mut Grid, rule: &SetCollapseRule<S, Grid, UniformSetCollapseObserver>) -> (usize, usize) {
+	let directions = rule.neighbor_offsets();
+	let mut neighbors = vec![None; directions.len()];
+	let mut neighbor_states = vec![None; directions.len()];
+	let mut queue: VecDeque<_> = grid.coordinate_list().iter().copied().filter(|coord| grid[*coord].entropy() != 0).collect();
+	let mut queued = vec![0usize; (SIZE * SIZE) as usize];
+	for coord in &queue {
+		queued[grid.index_of(*coord).unwrap()] += 1;
+	}
+	let (mut collapses, mut repeats) = (0, 0);
+	while let Some(coord) = queue.pop_front() {
+		queued[grid.index_of(coord).unwrap()] -= 1;
+		let entropy_before = grid[coord].entropy();
+		if entropy_before == 0 {
+			continue;
+		}
+		grid.neighbors(coord, &directions, &mut neighbors);
+		for (neighbor, state) in neighbors.iter().zip(neighbor_states.iter_mut()) {
+			*state = neighbor.map(|neighbor| grid[neighbor]);
+		}
+		collapses += 1;
+		rule.collapse(&mut grid[coord], &neighbor_states);
+		assert!(!grid[coord].is_contradiction());
+		if grid[coord].entropy() < entropy_before {
+			for neighbor in neighbors.iter().flatten() {
+				if grid[*neighbor].entropy() != 0 {
+					let index = grid.index_of(*neighbor).unwrap();
+					if queued[index] > 0 {
+						repeats += 1;
+					}
+					queued[index] += 1;
+					queue.push_back(*neighbor);
+				}
+			}
+		}
+	}
+	(collapses, repeats)
+}
+
+fn run_repeated_queueing(rule: &SetCollapseRule<S, Grid, UniformSetCollapseObserver>) {
+	// the collapser times also include its other bookkeeping, such as keeping
+	// the set of unresolved cells
+	println!("initial propagation from a tile every 8 cells:");
+	
+	let mut plain = seeded_grid();
+	let start = Instant::now();
+	let (collapses, repeats) = propagate_with_plain_queue(&mut plain, rule);
+	println!("  plain queue:");
+	println!("    collapse() calls: {} ({} cells queued while already waiting)", collapses, repeats);
+	println!("    time: {:?}", start.elapsed());
+	
+	let mut indexed = seeded_grid();
+	let start = Instant::now();
+	let stats = Collapser::new(&mut indexed, rule).with_heuristic(PropagateOnly).run().unwrap();
+	println!("  deduplicated queue, tracked by Space::index_of:");
+	println!("    collapse() calls: {}", stats.rule_collapses);
+	println!("    time: {:?}", start.elapsed());
+	
+	let mut unindexed = Unindexed(seeded_grid());
+	let unindexed_rule = self::rule::<Unindexed>();
+	let start = Instant::now();
+	let stats = Collapser::new(&mut unindexed, &unindexed_rule).with_heuristic(PropagateOnly).run().unwrap();
+	println!("  deduplicated queue, tracked in a hash set:");
+	println!("    collapse() calls: {}", stats.rule_collapses);
+	println!("    time: {:?}", start.elapsed());
+	
+	// propagation reaches the same fixed point whatever order cells are
+	// visited in
+	let cells = |grid: &Grid| grid.coordinate_list().iter().map(|coord| grid[*coord]).collect::<Vec<_>>();
+	assert_eq!(cells(&plain), cells(&indexed));
+	assert_eq!(cells(&plain), cells(&unindexed.0));
+}
+
+fn main() {
+	let rule = rule::<Grid>();
+	println!("{}x{} grid of BitsetState<11>", SIZE, SIZE);
+	run(&rule, false);
+	run(&rule, true);
+	run_repeated_queueing(&rule);
+}
@@ -37,12 +37,12 @@ fn main() {
 		.build();
 	
 	let mut space = SquareGrid::new(20, 10, |_, _| S::all());
-	kahuna::collapse(&mut space, &rule);
+	kahuna::collapse(&mut space, &rule).expect("failed to collapse space");
 	for y in 0..10 {
 		for x in 0..20 {
 			print!("{} ", to_char(&space[(x, y)]));
 		}
-		println!("");
+		println!();
 	}
-	println!("");
+	println!();
 }
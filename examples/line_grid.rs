@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use kahuna::*;
 use kahuna::square_grid::*;
-use rand::{thread_rng, Rng};
+use rand::{Rng, RngCore};
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 struct States(u32);
@@ -28,8 +28,12 @@ impl Display for States {
 impl State for States {
     fn entropy(&self) -> u32 {
 		let States(x) = *self;
-        x.count_ones() - 1
+        x.count_ones().saturating_sub(1)
     }
+	
+	fn is_contradiction(&self) -> bool {
+		self.0 == 0
+	}
 }
 
 type Grid = SquareGrid<States>;
@@ -49,11 +53,11 @@ impl CollapseRule<States, Grid> for Rule {
 	fn collapse(&self, cell: &mut States, neighbors: &[Option<States>]) {
 		let States(x) = cell;
 		
-		for rule in &RULES[..] {
+		for rule in RULES {
 			if *x & rule.state != 0 {
-				for i in 0 .. 4 {
-					if let Some(States(neighbor)) = neighbors[i] {
-						if neighbor & rule.allowed_neighbors[i] == 0 {
+				for (neighbor, allowed) in neighbors.iter().zip(rule.allowed_neighbors) {
+					if let Some(States(neighbor)) = neighbor {
+						if neighbor & allowed == 0 {
 							*x &= !rule.state;
 						}
 					}
@@ -62,7 +66,7 @@ impl CollapseRule<States, Grid> for Rule {
 		}
 	}
 	
-	fn observe(&self, cell: &mut States, _neighbors: &[Option<States>]) {
+	fn observe(&self, cell: &mut States, _neighbors: &[Option<States>], rng: &mut dyn RngCore) {
 		let States(x) = cell;
 		let mut bits = vec![];
 		for i in 0 .. 4 {
@@ -70,7 +74,7 @@ impl CollapseRule<States, Grid> for Rule {
 				bits.push(i);
 			}
 		}
-		*x = 1 << bits[thread_rng().gen_range(0..bits.len())];
+		*x = 1 << bits[rng.gen_range(0..bits.len())];
 	}
 }
 
@@ -79,7 +83,7 @@ struct StateRule {
 	allowed_neighbors: [u32; 4]
 }
 
-const RULES: &'static [StateRule] = &[
+const RULES: &[StateRule] = &[
 	StateRule {
 		state: ST_CORNER,
 		allowed_neighbors: [
@@ -116,11 +120,11 @@ const RULES: &'static [StateRule] = &[
 
 fn main() {
 	let mut grid = Grid::new(40, 20, |_, _| States(ST_ALL));
-	collapse(&mut grid, &Rule);
+	collapse(&mut grid, &Rule).expect("failed to collapse grid");
 	for y in 0..20 {
 		for x in 0..40 {
 			print!("{}", grid[(x, y)]);
 		}
-		println!("");
+		println!();
 	}
 }
\ No newline at end of file
@@ -87,7 +87,7 @@ fn main() {
 		])
 		.build();
 		let mut grid = Grid::new(WIDTH_TILES as isize, HEIGHT_TILES as isize, |_, _| S::all());
-	collapse(&mut grid, &rule);
+	collapse(&mut grid, &rule).expect("failed to collapse grid");
 	
 	let image_bytes = include_bytes!("pattern.png");
 	let input_image = image::load_from_memory_with_format(&image_bytes[..], ImageFormat::Png).unwrap().into_rgb8();
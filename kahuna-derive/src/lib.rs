@@ -0,0 +1,230 @@
+//! Derive macros for [kahuna](https://crates.io/crates/kahuna). These are
+//! re-exported by kahuna with its `derive` feature, which is the intended way
+//! to use them.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+/// Derives a set-state type for a fieldless enum of tiles, named after the
+/// enum with `Set` on the end (so `Tile` gets `TileSet`).
+/// 
+/// The set type implements `State`, `SetState` and `AllState` so it can be
+/// used with `SetCollapseRule`, converts from a tile with `From`, and back
+/// into the tile it collapsed to with `TryFrom` or `tile()`. Tiles and sets
+/// can be combined with `|`, and sets display as the tile name once
+/// collapsed.
+#[proc_macro_derive(Tiles)]
+pub fn derive_tiles(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	match tiles(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(error) => error.to_compile_error().into(),
+	}
+}
+
+fn tiles(input: &DeriveInput) -> Result<TokenStream2, Error> {
+	let Data::Enum(data) = &input.data else {
+		return Err(Error::new_spanned(&input.ident, "Tiles can only be derived for enums"));
+	};
+	if !input.generics.params.is_empty() {
+		return Err(Error::new_spanned(&input.generics, "Tiles can't be derived for generic enums"));
+	}
+	if data.variants.is_empty() {
+		return Err(Error::new_spanned(&input.ident, "Tiles needs at least one variant"));
+	}
+	for variant in &data.variants {
+		if !matches!(variant.fields, Fields::Unit) {
+			return Err(Error::new_spanned(variant, "Tiles variants can't have fields"));
+		}
+	}
+	
+	let vis = &input.vis;
+	let tile = &input.ident;
+	let set = format_ident!("{}Set", tile);
+	let variants: Vec<_> = data.variants.iter().map(|variant| &variant.ident).collect();
+	let names: Vec<_> = variants.iter().map(|variant| variant.to_string()).collect();
+	let indices: Vec<_> = (0..variants.len() as u32).collect();
+	let count = variants.len();
+	let final_state_count = count as u32;
+	// one u64 holds up to 64 tiles, past that they need more words
+	let state = if count <= 64 {
+		quote!(::kahuna::bitset_state::BitsetState<#final_state_count>)
+	} else {
+		let words = count.div_ceil(64);
+		quote!(::kahuna::wide_bitset_state::WideBitsetState<#final_state_count, #words>)
+	};
+	let doc = format!("Set of possible [{}] tiles, derived by `#[derive(Tiles)]`", tile);
+	
+	Ok(quote! {
+		#[doc = #doc]
+		#[derive(PartialEq, Eq, Copy, Clone, Hash, Debug)]
+		#vis struct #set(#state);
+		
+		impl #set {
+			/// Every tile, in the order they were declared
+			pub const TILES: [#tile; #count] = [#(#tile::#variants),*];
+			const NAMES: [&'static str; #count] = [#(#names),*];
+			
+			const fn index(tile: &#tile) -> u32 {
+				match tile {
+					#(#tile::#variants => #indices,)*
+				}
+			}
+			
+			/// Creates a set with just `tile` inside
+			pub const fn new(tile: #tile) -> Self {
+				Self(<#state>::state(Self::index(&tile)))
+			}
+			
+			/// Creates a set with each tile in `tiles` inside
+			pub fn with_tiles(tiles: &[#tile]) -> Self {
+				let indices: Vec<u32> = tiles.iter().map(Self::index).collect();
+				Self(<#state>::with_states(&indices))
+			}
+			
+			/// Checks if `tile` is inside
+			pub fn contains(&self, tile: &#tile) -> bool {
+				::kahuna::SetState::has_any_of(&self.0, &<#state>::state(Self::index(tile)))
+			}
+			
+			/// Iterates over the tiles inside, in the order they were declared
+			pub fn tiles(&self) -> impl Iterator<Item = #tile> {
+				let set = *self;
+				Self::TILES.into_iter().filter(move |tile| set.contains(tile))
+			}
+			
+			/// The tile this set has collapsed to, if there's only one inside
+			pub fn tile(&self) -> Option<#tile> {
+				let mut tiles = self.tiles();
+				match (tiles.next(), tiles.next()) {
+					(Some(tile), None) => Some(tile),
+					_ => None,
+				}
+			}
+		}
+		
+		impl ::kahuna::State for #set {
+			fn entropy(&self) -> u32 {
+				::kahuna::State::entropy(&self.0)
+			}
+			
+			fn is_contradiction(&self) -> bool {
+				::kahuna::State::is_contradiction(&self.0)
+			}
+		}
+		
+		impl ::kahuna::SetState for #set {
+			fn set_states(&mut self, states: &Self) {
+				::kahuna::SetState::set_states(&mut self.0, &states.0)
+			}
+			
+			fn has_any_of(&self, states: &Self) -> bool {
+				::kahuna::SetState::has_any_of(&self.0, &states.0)
+			}
+			
+			fn clear_states(&mut self, states: &Self) {
+				::kahuna::SetState::clear_states(&mut self.0, &states.0)
+			}
+			
+			fn retain_states(&mut self, states: &Self) {
+				::kahuna::SetState::retain_states(&mut self.0, &states.0)
+			}
+			
+			fn collect_final_states(&self, states: &mut Vec<Self>) {
+				let mut inner = Vec::new();
+				::kahuna::SetState::collect_final_states(&self.0, &mut inner);
+				states.extend(inner.into_iter().map(#set));
+			}
+			
+			fn is_empty(&self) -> bool {
+				::kahuna::SetState::is_empty(&self.0)
+			}
+		}
+		
+		impl ::kahuna::AllState for #set {
+			fn all() -> Self {
+				Self(<#state as ::kahuna::AllState>::all())
+			}
+		}
+		
+		impl ::core::convert::From<#tile> for #set {
+			fn from(tile: #tile) -> Self {
+				Self::new(tile)
+			}
+		}
+		
+		impl ::core::convert::TryFrom<#set> for #tile {
+			type Error = #set;
+			
+			fn try_from(set: #set) -> Result<Self, #set> {
+				set.tile().ok_or(set)
+			}
+		}
+		
+		impl ::core::ops::BitOr for #set {
+			type Output = Self;
+			
+			fn bitor(self, rhs: Self) -> Self::Output {
+				Self(self.0 | rhs.0)
+			}
+		}
+		
+		impl ::core::ops::BitOr<#tile> for #set {
+			type Output = Self;
+			
+			fn bitor(self, rhs: #tile) -> Self::Output {
+				self | Self::new(rhs)
+			}
+		}
+		
+		impl ::core::ops::BitOr for #tile {
+			type Output = #set;
+			
+			fn bitor(self, rhs: Self) -> Self::Output {
+				#set::new(self) | #set::new(rhs)
+			}
+		}
+		
+		impl ::core::ops::BitOr<#set> for #tile {
+			type Output = #set;
+			
+			fn bitor(self, rhs: #set) -> Self::Output {
+				#set::new(self) | rhs
+			}
+		}
+		
+		impl ::core::ops::BitAnd for #set {
+			type Output = Self;
+			
+			fn bitand(self, rhs: Self) -> Self::Output {
+				Self(self.0 & rhs.0)
+			}
+		}
+		
+		impl ::core::ops::BitXor for #set {
+			type Output = Self;
+			
+			fn bitxor(self, rhs: Self) -> Self::Output {
+				Self(self.0 ^ rhs.0)
+			}
+		}
+		
+		impl ::core::fmt::Display for #set {
+			fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+				if let Some(tile) = self.tile() {
+					return write!(f, "{}", Self::NAMES[Self::index(&tile) as usize]);
+				}
+				write!(f, "{{")?;
+				for (i, tile) in self.tiles().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+					write!(f, "{}", Self::NAMES[Self::index(&tile) as usize])?;
+				}
+				write!(f, "}}")
+			}
+		}
+	})
+}
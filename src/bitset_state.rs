@@ -6,7 +6,7 @@ use crate::{SetState, State, AllState};
 /// 
 /// * `FINAL_STATE_COUNT` - the total number of final (fully collapsed) states
 #[derive(PartialEq, Eq, Copy, Clone, Hash, Debug)]
-pub struct BitsetState<const FINAL_STATE_COUNT: u32>(u64);
+pub struct BitsetState<const FINAL_STATE_COUNT: u32>(pub(crate) u64);
 
 impl<const FINAL_STATE_COUNT: u32> BitsetState<FINAL_STATE_COUNT> {
 	/// Creates the `n`th unique state
@@ -17,7 +17,7 @@ impl<const FINAL_STATE_COUNT: u32> BitsetState<FINAL_STATE_COUNT> {
 	/// Creates a state representing the states numbered by members of `states`
 	pub fn with_states(states: &[u32]) -> Self {
 		let mut x: u64 = 0;
-		for i in &states[..] {
+		for i in states {
 			assert!(*i < FINAL_STATE_COUNT);
 			x |= 1u64 << i;
 		}
@@ -50,8 +50,12 @@ impl<const FINAL_STATE_COUNT: u32> AllState for BitsetState<FINAL_STATE_COUNT> {
 impl<const FINAL_STATE_COUNT: u32> State for BitsetState<FINAL_STATE_COUNT> {
     fn entropy(&self) -> u32 {
         let BitsetState(x) = *self;
-		x.count_ones() - 1
+		x.count_ones().saturating_sub(1)
     }
+	
+	fn is_contradiction(&self) -> bool {
+		self.is_empty()
+	}
 }
 
 impl<const FINAL_STATE_COUNT: u32> SetState for BitsetState<FINAL_STATE_COUNT> {
@@ -63,6 +67,10 @@ impl<const FINAL_STATE_COUNT: u32> SetState for BitsetState<FINAL_STATE_COUNT> {
 		self.0 &= !states.0
 	}
 	
+	fn retain_states(&mut self, states: &Self) {
+		self.0 &= states.0
+	}
+	
 	fn set_states(&mut self, states: &Self) {
 		self.0 |= states.0
 	}
@@ -76,6 +84,10 @@ impl<const FINAL_STATE_COUNT: u32> SetState for BitsetState<FINAL_STATE_COUNT> {
 			}
 		}
 	}
+	
+	fn is_empty(&self) -> bool {
+		self.0 == 0
+	}
 }
 
 impl<const FINAL_STATE_COUNT: u32> BitOr for BitsetState<FINAL_STATE_COUNT> {
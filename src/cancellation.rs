@@ -0,0 +1,23 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+/// Shared flag for stopping collapse from elsewhere, such as another thread.
+/// See [crate::Collapser::with_cancellation].
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+	/// Create a new CancellationToken, which hasn't been cancelled
+	pub fn new() -> Self {
+		Self::default()
+	}
+	
+	/// Ask any collapse using this token to stop
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+	
+	/// Checks if [Self::cancel] has been called
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
@@ -0,0 +1,143 @@
+//! Endless worlds generated chunk by chunk
+
+use std::collections::BTreeMap;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{State, CollapseRule, Collapser, Contradiction, derive_seed};
+use crate::square_grid::SquareGrid;
+
+/// An unbounded 2d world made of fixed-size [SquareGrid] chunks, which are
+/// generated on demand.
+/// 
+/// When a chunk is generated, the cells of any neighboring chunks which have
+/// already been generated are copied in around it, so that it fits with them
+/// seamlessly. Each chunk gets its own rng seeded from the world seed and the
+/// chunk coordinate, so generating the same chunks in the same order with the
+/// same seed always gives the same world.
+/// 
+/// Cells are addressed with world coordinates, and chunks with chunk
+/// coordinates, where chunk `(0, 0)` covers world coordinates from `(0, 0)` to
+/// `(chunk_width - 1, chunk_height - 1)`.
+pub struct ChunkedWorld<St: State + 'static, Rule: CollapseRule<St, SquareGrid<St>>> {
+	chunks: BTreeMap<(isize, isize), SquareGrid<St>>,
+	chunk_width: isize,
+	chunk_height: isize,
+	margin: isize,
+	rule: Rule,
+	seed: u64,
+	init_fn: Box<dyn Fn(isize, isize) -> St>,
+	max_attempts: usize,
+	max_backtracks: Option<usize>,
+}
+
+impl<St: State + 'static, Rule: CollapseRule<St, SquareGrid<St>>> ChunkedWorld<St, Rule> {
+	/// Create a new ChunkedWorld with no chunks generated yet
+	/// 
+	/// * `chunk_width` - width of each chunk
+	/// * `chunk_height` - height of each chunk
+	/// * `rule` - rule to collapse chunks with
+	/// * `seed` - seed for the whole world
+	/// * `init_fn` - callback to set the initial state of each cell based on
+	///   world coordinate
+	pub fn new(chunk_width: isize, chunk_height: isize, rule: Rule, seed: u64, init_fn: impl Fn(isize, isize) -> St + 'static) -> Self {
+		assert!(chunk_width > 0 && chunk_height > 0);
+		let margin = rule.neighbor_offsets().iter()
+			.map(|(dx, dy)| dx.abs().max(dy.abs()))
+			.max()
+			.unwrap_or(0);
+		Self {
+			chunks: BTreeMap::new(),
+			chunk_width,
+			chunk_height,
+			margin,
+			rule,
+			seed,
+			init_fn: Box::new(init_fn),
+			max_attempts: 1,
+			max_backtracks: None,
+		}
+	}
+	
+	/// Try generating each chunk up to `max_attempts` times, with a different
+	/// seed each time, before giving up on it.
+	pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+		self.max_attempts = max_attempts;
+		self
+	}
+	
+	/// Enables backtracking when generating chunks. See
+	/// [crate::collapse_backtracking].
+	pub fn with_backtracking(mut self, max_backtracks: usize) -> Self {
+		self.max_backtracks = Some(max_backtracks);
+		self
+	}
+	
+	/// Get the coordinate of the chunk containing the cell at world coordinate
+	/// `(x, y)`
+	pub fn chunk_coordinate(&self, x: isize, y: isize) -> (isize, isize) {
+		(x.div_euclid(self.chunk_width), y.div_euclid(self.chunk_height))
+	}
+	
+	/// Get a chunk, if it has been generated
+	pub fn chunk(&self, chunk: (isize, isize)) -> Option<&SquareGrid<St>> {
+		self.chunks.get(&chunk)
+	}
+	
+	/// Get the cell at world coordinate `(x, y)`, if its chunk has been
+	/// generated
+	pub fn get(&self, x: isize, y: isize) -> Option<&St> {
+		let (cx, cy) = self.chunk_coordinate(x, y);
+		self.chunks.get(&(cx, cy)).map(|grid| &grid[(x - cx * self.chunk_width, y - cy * self.chunk_height)])
+	}
+	
+	/// Get a chunk, generating it first if needed.
+	/// 
+	/// Returns the last [Contradiction] if the chunk couldn't be generated, with
+	/// its coordinate in world coordinates. The chunk is left ungenerated in
+	/// that case.
+	pub fn generate_chunk(&mut self, chunk: (isize, isize)) -> Result<&SquareGrid<St>, Contradiction<(isize, isize), St>> {
+		if !self.chunks.contains_key(&chunk) {
+			let grid = self.build_chunk(chunk)?;
+			self.chunks.insert(chunk, grid);
+		}
+		Ok(&self.chunks[&chunk])
+	}
+	
+	fn build_chunk(&self, chunk: (isize, isize)) -> Result<SquareGrid<St>, Contradiction<(isize, isize), St>> {
+		let (cx, cy) = chunk;
+		let margin = self.margin;
+		let (origin_x, origin_y) = (cx * self.chunk_width - margin, cy * self.chunk_height - margin);
+		// the chunk is collapsed in the middle of a larger grid, with the
+		// cells of its neighbors around it
+		let init_fn = |x: isize, y: isize| {
+			let (world_x, world_y) = (origin_x + x, origin_y + y);
+			self.get(world_x, world_y).cloned().unwrap_or_else(|| (self.init_fn)(world_x, world_y))
+		};
+		let chunk_seed = derive_seed(derive_seed(self.seed, cx as u64), cy as u64);
+		let mut attempt = 0;
+		loop {
+			let mut grid = SquareGrid::new(self.chunk_width + 2 * margin, self.chunk_height + 2 * margin, init_fn);
+			let region = grid.rect(margin, margin, self.chunk_width, self.chunk_height);
+			let rng = StdRng::seed_from_u64(derive_seed(chunk_seed, attempt as u64));
+			let mut collapser = Collapser::new(&mut grid, &self.rule).with_rng(rng).with_region(&region);
+			if let Some(max_backtracks) = self.max_backtracks {
+				collapser = collapser.with_backtracking(max_backtracks);
+			}
+			let result = collapser.run();
+			drop(collapser);
+			attempt += 1;
+			match result {
+				Ok(_) => return Ok(SquareGrid::new(self.chunk_width, self.chunk_height, |x, y| grid[(x + margin, y + margin)].clone())),
+				Err(contradiction) if attempt >= self.max_attempts => {
+					let (x, y) = contradiction.coordinate;
+					return Err(Contradiction {
+						coordinate: (origin_x + x, origin_y + y),
+						neighbors: contradiction.neighbors,
+					});
+				},
+				Err(_) => {},
+			}
+		}
+	}
+}
@@ -0,0 +1,74 @@
+use crate::{State, Space, CollapseStats, Contradiction};
+
+/// Receives events as a space is collapsed, for things like progress bars or
+/// debugging views. Every method does nothing by default, so only the events
+/// of interest need to be implemented.
+/// 
+/// When backtracking, cells can regain states which were reported as removed
+/// before.
+pub trait CollapseListener<St: State, Sp: Space<St>> {
+	/// The cell at `coord` was observed, and chose `state`
+	fn observed(&mut self, coord: Sp::Coordinate, state: &St) {
+		let _ = (coord, state);
+	}
+	/// The cell at `coord` lost possible states through propagation or a
+	/// restriction, leaving `state`
+	fn reduced(&mut self, coord: Sp::Coordinate, state: &St) {
+		let _ = (coord, state);
+	}
+	/// An observation has been propagated, leaving `unresolved_count` cells
+	/// to resolve
+	fn progress(&mut self, unresolved_count: usize) {
+		let _ = unresolved_count;
+	}
+	/// The observation of `banned` at `coord` was undone because it led to a
+	/// contradiction
+	fn backtracked(&mut self, coord: Sp::Coordinate, banned: &St) {
+		let _ = (coord, banned);
+	}
+	/// A cell ran out of possible states. This is reported even if the
+	/// contradiction is then resolved by backtracking.
+	fn contradiction(&mut self, contradiction: &Contradiction<Sp::Coordinate, St>) {
+		let _ = contradiction;
+	}
+	/// A step was interrupted partway through propagating, and the next step
+	/// carries on from there. See [crate::Collapser::is_interrupted].
+	fn interrupted(&mut self) {}
+	/// Every cell has been resolved
+	fn completed(&mut self, stats: &CollapseStats) {
+		let _ = stats;
+	}
+}
+
+/// Listener which ignores every event
+impl<St: State, Sp: Space<St>> CollapseListener<St, Sp> for () {}
+
+impl<St: State, Sp: Space<St>, L: CollapseListener<St, Sp> + ?Sized> CollapseListener<St, Sp> for &mut L {
+	fn observed(&mut self, coord: Sp::Coordinate, state: &St) {
+		(**self).observed(coord, state)
+	}
+	
+	fn reduced(&mut self, coord: Sp::Coordinate, state: &St) {
+		(**self).reduced(coord, state)
+	}
+	
+	fn progress(&mut self, unresolved_count: usize) {
+		(**self).progress(unresolved_count)
+	}
+	
+	fn backtracked(&mut self, coord: Sp::Coordinate, banned: &St) {
+		(**self).backtracked(coord, banned)
+	}
+	
+	fn contradiction(&mut self, contradiction: &Contradiction<Sp::Coordinate, St>) {
+		(**self).contradiction(contradiction)
+	}
+	
+	fn interrupted(&mut self) {
+		(**self).interrupted()
+	}
+	
+	fn completed(&mut self, stats: &CollapseStats) {
+		(**self).completed(stats)
+	}
+}
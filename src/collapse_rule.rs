@@ -1,4 +1,6 @@
-use crate::{State, Space};
+use rand::RngCore;
+
+use crate::{State, Space, ParallelBounds};
 
 /// Collapse rules define the relationships between a cell's possible state
 /// based on it's neighbors.
@@ -8,7 +10,7 @@ use crate::{State, Space};
 /// possible states that a cell can take on. With addative rules, runtime
 /// can be unbounded, the algorithm may (randomly) never converge on a
 /// solution.
-pub trait CollapseRule<S: State, Sp: 'static + Space<S>> {
+pub trait CollapseRule<S: State, Sp: 'static + Space<S>>: ParallelBounds {
 	/// Neighbor directions are specified as a list of coordinate deltas.
 	fn neighbor_offsets(&self) -> Box<[Sp::CoordinateDelta]>;
 	/// The collapse rule, which modifies the possible states of 'cell' based
@@ -16,12 +18,38 @@ pub trait CollapseRule<S: State, Sp: 'static + Space<S>> {
 	/// 
 	/// * `cell` - The cell state to modify
 	/// * `neighbors` - The states of neighbors in the order specified by
-	/// `NEIGHBOR_DIRECTIONS`. `Some(<state>)` if the cell exists, and `None`
-	/// otherwise.
+	///   `neighbor_offsets()`. `Some(<state>)` if the cell exists, and `None`
+	///   otherwise.
 	fn collapse(&self, cell: &mut S, neighbors: &[Option<S>]);
 	/// The observe rule, which forces a cell into a zero-entropy state.
 	/// 
 	/// * `cell` - The cell to observe
 	/// * `neighbors` - The states of neighbor cells as in `collapse()` above.
-	fn observe(&self, cell: &mut S, neighbors: &[Option<S>]);
+	/// * `rng` - Source of randomness for the observation. To keep collapse
+	///   reproducible, this should be the only randomness used.
+	fn observe(&self, cell: &mut S, neighbors: &[Option<S>], rng: &mut dyn RngCore);
+	/// The ban rule, used when backtracking to remove an observed state which
+	/// led to a contradiction from the cell's possible states.
+	/// 
+	/// * `cell` - The cell state as it was before being observed
+	/// * `observed` - The state `cell` was observed to be
+	/// 
+	/// Returns `false` if the rule can't express this, in which case the cell
+	/// is left as it was and will be observed again.
+	fn ban(&self, cell: &mut S, observed: &S) -> bool {
+		let _ = (cell, observed);
+		false
+	}
+	/// Weight-aware entropy of a cell, such as the Shannon entropy of the
+	/// cell's possible states given how likely `observe()` is to pick each of
+	/// them. When this is provided, cells are selected for observation by
+	/// lowest weighted entropy rather than by [State::entropy]. For seeded
+	/// collapse to be reproducible across platforms, this should give exactly
+	/// the same result everywhere, which [f32::ln] doesn't promise.
+	/// 
+	/// Returns `None` by default.
+	fn weighted_entropy(&self, cell: &S) -> Option<f32> {
+		let _ = cell;
+		None
+	}
 }
@@ -0,0 +1,1031 @@
+use std::{collections::{BTreeSet, HashSet, VecDeque}, hash::Hash, time::Instant};
+
+use rand::{thread_rng, RngCore};
+
+use crate::{State, SetState, Space, CollapseRule, CollapseListener, CollapseStats, Propagator, GlobalConstraint, CancellationToken, Contradiction, PinError, StaleCheckpoint, SelectionHeuristic, SelectionContext};
+use crate::heuristics::MinimumEntropy;
+
+/// An observation made during collapse, recorded so that it can be undone
+/// when backtracking.
+struct Decision<C, St> {
+	coordinate: C,
+	observed: St,
+	journal_len: usize,
+}
+
+/// A restriction made with [Collapser::restrict], recorded so that it can be
+/// made again when backtracking undoes it along with an earlier observation.
+struct Restriction<C, St> {
+	coordinate: C,
+	states: St,
+	apply: fn(&mut St, &St),
+	journal_len: usize,
+}
+
+/// How many cells are propagated between checks for cancellation and the
+/// deadline
+#[cfg(not(feature = "parallel"))]
+const INTERRUPT_CHECK_INTERVAL: usize = 64;
+
+fn is_interrupted(cancellation: &Option<CancellationToken>, deadline: Option<Instant>) -> bool {
+	cancellation.as_ref().is_some_and(|token| token.is_cancelled()) ||
+		deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// A point during collapse which [Collapser::rollback] can put the space back
+/// to. See [Collapser::checkpoint].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint<C> {
+	id: u64,
+	journal_len: usize,
+	decisions_len: usize,
+	initialized: bool,
+	stopped: bool,
+	done: bool,
+	failed: bool,
+	last_observed: Option<C>,
+}
+
+/// Queue of cells waiting to be propagated, which holds each cell at most
+/// once. Cells are tracked by [Space::index_of] where the space provides it.
+struct PropogationQueue<C> {
+	queue: VecDeque<(C, Option<usize>)>,
+	queued_indices: Vec<bool>,
+	queued_coordinates: HashSet<C>,
+}
+
+impl<C: Hash + Eq + Copy> PropogationQueue<C> {
+	fn new() -> Self {
+		Self {
+			queue: VecDeque::new(),
+			queued_indices: Vec::new(),
+			queued_coordinates: HashSet::new(),
+		}
+	}
+	
+	/// Adds `coord` to the back of the queue, unless it's already waiting
+	fn push(&mut self, coord: C, index: Option<usize>) {
+		let newly_queued = match index {
+			Some(index) => {
+				if index >= self.queued_indices.len() {
+					self.queued_indices.resize(index + 1, false);
+				}
+				!std::mem::replace(&mut self.queued_indices[index], true)
+			},
+			None => self.queued_coordinates.insert(coord),
+		};
+		if newly_queued {
+			self.queue.push_back((coord, index));
+		}
+	}
+	
+	#[cfg(not(feature = "parallel"))]
+	fn len(&self) -> usize {
+		self.queue.len()
+	}
+	
+	#[cfg(not(feature = "parallel"))]
+	fn is_empty(&self) -> bool {
+		self.queue.is_empty()
+	}
+	
+	fn pop(&mut self) -> Option<C> {
+		let (coord, index) = self.queue.pop_front()?;
+		match index {
+			Some(index) => self.queued_indices[index] = false,
+			None => {
+				self.queued_coordinates.remove(&coord);
+			},
+		}
+		Some(coord)
+	}
+	
+	fn clear(&mut self) {
+		while self.pop().is_some() {}
+	}
+}
+
+/// Drives the wave function collapse algorithm over a space, one observation
+/// at a time.
+/// 
+/// This is what [crate::collapse] uses internally, but it can also be used
+/// directly to spread generation out over time, or to look at the space
+/// between steps.
+pub struct Collapser<'a, St: State, Sp: Space<St>, Rule: CollapseRule<St, Sp>> {
+	space: &'a mut Sp,
+	rule: &'a Rule,
+	rng: Box<dyn RngCore + 'a>,
+	heuristic: Box<dyn SelectionHeuristic<St, Sp> + 'a>,
+	listener: Box<dyn CollapseListener<St, Sp> + 'a>,
+	propagator: Option<Box<dyn Propagator<St, Sp> + 'a>>,
+	constraints: Vec<Box<dyn GlobalConstraint<St, Sp> + 'a>>,
+	last_observed: Option<Sp::Coordinate>,
+	neighbor_directions: Box<[Sp::CoordinateDelta]>,
+	neighbors: Box<[Option<Sp::Coordinate>]>,
+	neighbor_states: Box<[Option<St>]>,
+	unresolved_set: BTreeSet<Sp::Coordinate>,
+	region: Option<BTreeSet<Sp::Coordinate>>,
+	to_propogate: PropogationQueue<Sp::Coordinate>,
+	journal: Option<Vec<(Sp::Coordinate, St)>>,
+	decisions: Vec<Decision<Sp::Coordinate, St>>,
+	restrictions: Vec<Restriction<Sp::Coordinate, St>>,
+	/// The id and journal length of each checkpoint which can still be rolled
+	/// back to
+	checkpoints: Vec<(u64, usize)>,
+	next_checkpoint: u64,
+	cancellation: Option<CancellationToken>,
+	deadline: Option<Instant>,
+	stopped: bool,
+	max_backtracks: usize,
+	stats: CollapseStats,
+	initialized: bool,
+	done: bool,
+	contradiction: Option<Contradiction<Sp::Coordinate, St>>,
+}
+
+impl<'a, St: State, Sp: Space<St>, Rule: CollapseRule<St, Sp>> Collapser<'a, St, Sp, Rule> {
+	/// Create a new Collapser which will collapse `space` in place using
+	/// `rule`. Nothing is modified until the first call to [Self::step].
+	pub fn new(space: &'a mut Sp, rule: &'a Rule) -> Self {
+		let neighbor_directions = rule.neighbor_offsets();
+		let neighbors = vec![None; neighbor_directions.len()].into_boxed_slice();
+		let neighbor_states = vec![None; neighbor_directions.len()].into_boxed_slice();
+		Self {
+			space,
+			rule,
+			rng: Box::new(thread_rng()),
+			heuristic: Box::new(MinimumEntropy::new()),
+			listener: Box::new(()),
+			propagator: None,
+			constraints: Vec::new(),
+			last_observed: None,
+			neighbor_directions,
+			neighbors,
+			neighbor_states,
+			unresolved_set: BTreeSet::new(),
+			region: None,
+			to_propogate: PropogationQueue::new(),
+			journal: None,
+			decisions: Vec::new(),
+			restrictions: Vec::new(),
+			checkpoints: Vec::new(),
+			next_checkpoint: 0,
+			cancellation: None,
+			deadline: None,
+			stopped: false,
+			max_backtracks: 0,
+			stats: CollapseStats {
+				attempts: 1,
+				..CollapseStats::default()
+			},
+			initialized: false,
+			done: false,
+			contradiction: None,
+		}
+	}
+	
+	/// Use `rng` as the source of randomness instead of the thread rng. See
+	/// [crate::collapse_with_rng].
+	pub fn with_rng(mut self, rng: impl RngCore + 'a) -> Self {
+		self.rng = Box::new(rng);
+		self
+	}
+	
+	/// Use `heuristic` to choose which cell to observe next, instead of
+	/// [MinimumEntropy]. See [crate::heuristics] for the built-in choices.
+	pub fn with_heuristic(mut self, heuristic: impl SelectionHeuristic<St, Sp> + 'a) -> Self {
+		self.heuristic = Box::new(heuristic);
+		self
+	}
+	
+	/// Report events to `listener` as the space is collapsed
+	pub fn with_listener(mut self, listener: impl CollapseListener<St, Sp> + 'a) -> Self {
+		self.listener = Box::new(listener);
+		self
+	}
+	
+	/// Propagate changes with `propagator`, instead of collapsing the
+	/// neighbors of each changed cell with the rule.
+	pub fn with_propagator(mut self, propagator: impl Propagator<St, Sp> + 'a) -> Self {
+		self.propagator = Some(Box::new(propagator));
+		self
+	}
+	
+	/// Add a constraint over the whole space, which is checked after every
+	/// change has been propagated. See [crate::constraints] for the built-in
+	/// constraints.
+	pub fn with_constraint(mut self, constraint: impl GlobalConstraint<St, Sp> + 'a) -> Self {
+		self.constraints.push(Box::new(constraint));
+		self
+	}
+	
+	/// Enables backtracking, undoing at most `max_backtracks` observations
+	/// which lead to contradictions before giving up. See
+	/// [crate::collapse_backtracking].
+	pub fn with_backtracking(mut self, max_backtracks: usize) -> Self {
+		self.journal = Some(Vec::new());
+		self.max_backtracks = max_backtracks;
+		self
+	}
+	
+	/// Only observe cells in `region`, leaving the rest of the space as it is
+	/// apart from states removed by propagation. See [crate::regenerate].
+	pub fn with_region(mut self, region: &[Sp::Coordinate]) -> Self {
+		self.region = Some(region.iter().copied().collect());
+		self
+	}
+	
+	/// Stop collapse once `token` is cancelled. See [Self::is_interrupted].
+	pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+		self.cancellation = Some(token);
+		self
+	}
+	
+	/// Stop collapse once `deadline` has passed. See [Self::is_interrupted].
+	pub fn with_deadline(mut self, deadline: Instant) -> Self {
+		self.deadline = Some(deadline);
+		self
+	}
+	
+	/// Replace the deadline, such as to resume collapse on the next tick of a
+	/// game loop.
+	pub fn set_deadline(&mut self, deadline: Instant) {
+		self.deadline = Some(deadline);
+	}
+	
+	/// Checks if the cancellation token has been cancelled or the deadline
+	/// has passed. This is checked between observations and while
+	/// propagating, including the initial propagation.
+	/// 
+	/// When a step is interrupted partway through propagating, which is
+	/// reported with [CollapseListener::interrupted], the cells still waiting
+	/// to be propagated are kept and the next step carries on with them
+	/// before observing anything else. Until then some cells can have states
+	/// left which propagation would remove. Propagation only ever removes
+	/// impossible states, so collapse can also be resumed with a new
+	/// collapser, which propagates the whole space again.
+	pub fn is_interrupted(&self) -> bool {
+		is_interrupted(&self.cancellation, self.deadline)
+	}
+	
+	/// The space being collapsed
+	pub fn space(&self) -> &Sp {
+		self.space
+	}
+	
+	/// The rule the space is being collapsed with
+	pub fn rule(&self) -> &Rule {
+		self.rule
+	}
+	
+	/// Statistics for the work done so far
+	pub fn stats(&self) -> CollapseStats {
+		self.stats
+	}
+	
+	/// Checks if every cell has been resolved. Once this is true, further
+	/// calls to [Self::step] do nothing.
+	pub fn is_done(&self) -> bool {
+		self.done
+	}
+	
+	/// Observe a single cell and propagate the result through the space. The
+	/// first step also propagates the initial state of the space before
+	/// observing anything, and a step after an interrupted one first finishes
+	/// its propagation. See [Self::is_interrupted].
+	/// 
+	/// Once a contradiction has been returned, every following step returns
+	/// it again. Does nothing if collapse has been interrupted.
+	pub fn step(&mut self) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		if let Some(contradiction) = &self.contradiction {
+			return Err(contradiction.clone());
+		}
+		if self.done || self.is_interrupted() {
+			return Ok(());
+		}
+		let result = self.try_step();
+		match &result {
+			Err(contradiction) => self.contradiction = Some(contradiction.clone()),
+			Ok(()) => if self.stopped {
+				self.listener.interrupted();
+			},
+		}
+		result
+	}
+	
+	/// Run at most `steps` steps, stopping early when the space is done or
+	/// collapse is interrupted.
+	pub fn run_for(&mut self, steps: usize) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		for _ in 0 .. steps {
+			if self.done || self.is_interrupted() {
+				break;
+			}
+			self.step()?;
+		}
+		Ok(())
+	}
+	
+	/// Run until every cell is resolved, or until collapse is interrupted.
+	/// Use [Self::is_done] to tell these apart.
+	pub fn run(&mut self) -> Result<CollapseStats, Contradiction<Sp::Coordinate, St>> {
+		while !self.done && !self.is_interrupted() {
+			self.step()?;
+		}
+		Ok(self.stats)
+	}
+	
+	/// Remove every state not in `states` from the cell at `coord`, and
+	/// propagate the change through the space straight away.
+	/// 
+	/// If this leaves some cell without any possible state, the space is put
+	/// back as it was and the [Contradiction] is returned, so the restriction
+	/// can be dropped or replaced with another. A successful restriction is
+	/// never undone by backtracking, and is made again whenever backtracking
+	/// undoes an observation made before it.
+	pub fn restrict(&mut self, coord: Sp::Coordinate, states: &St) -> Result<(), Contradiction<Sp::Coordinate, St>> where St: SetState {
+		self.modify(coord, states, |cell, states| cell.retain_states(states))
+	}
+	
+	/// Fix the cell at `coord` to the final state `state`. See [Self::restrict].
+	/// 
+	/// Returns [PinError::NotFinal] without changing anything if `state` isn't
+	/// a single final state.
+	pub fn pin(&mut self, coord: Sp::Coordinate, state: &St) -> Result<(), PinError<Sp::Coordinate, St>> where St: SetState {
+		if state.entropy() != 0 || state.is_contradiction() {
+			return Err(PinError::NotFinal);
+		}
+		Ok(self.restrict(coord, state)?)
+	}
+	
+	/// Save the current point of collapse, so that [Self::rollback] can undo
+	/// everything done after it. Only the cells modified since are kept, as a
+	/// journal of their previous states, which keeps growing until the
+	/// checkpoint is given to [Self::release].
+	pub fn checkpoint(&mut self) -> Checkpoint<Sp::Coordinate> {
+		let journal = self.journal.get_or_insert_with(Vec::new);
+		let id = self.next_checkpoint;
+		self.next_checkpoint += 1;
+		self.checkpoints.push((id, journal.len()));
+		Checkpoint {
+			id,
+			journal_len: journal.len(),
+			decisions_len: self.decisions.len(),
+			initialized: self.initialized,
+			stopped: self.stopped,
+			done: self.done,
+			failed: self.contradiction.is_some(),
+			last_observed: self.last_observed,
+		}
+	}
+	
+	/// Put every cell modified since `checkpoint` was taken back to its
+	/// previous state, so that collapse carries on from there.
+	/// 
+	/// Checkpoints taken after `checkpoint` can no longer be rolled back to,
+	/// and neither can checkpoints which have been released or which
+	/// backtracking has gone back past, which return [StaleCheckpoint]
+	/// without changing anything.
+	pub fn rollback(&mut self, checkpoint: Checkpoint<Sp::Coordinate>) -> Result<(), StaleCheckpoint> {
+		if !self.checkpoints.iter().any(|(id, _)| *id == checkpoint.id) {
+			return Err(StaleCheckpoint);
+		}
+		self.checkpoints.retain(|(id, _)| *id <= checkpoint.id);
+		self.undo(checkpoint.journal_len);
+		self.decisions.truncate(checkpoint.decisions_len);
+		self.restrictions.retain(|restriction| restriction.journal_len < checkpoint.journal_len);
+		self.last_observed = checkpoint.last_observed;
+		if !checkpoint.initialized {
+			self.initialized = false;
+			self.unresolved_set.clear();
+		}
+		self.done = checkpoint.done;
+		if !checkpoint.failed {
+			self.contradiction = None;
+		}
+		self.reset_caches();
+		self.stopped = checkpoint.stopped;
+		if self.stopped {
+			// the cells which were waiting to be propagated weren't kept, so
+			// propagate every cell again
+			for coord in self.unresolved_set.iter() {
+				self.to_propogate.push(*coord, self.space.index_of(*coord));
+			}
+		}
+		Ok(())
+	}
+	
+	/// Stop keeping what's needed to roll back to `checkpoint`. Once no
+	/// checkpoints are left, the journal is cut down to what backtracking
+	/// needs, or dropped if backtracking isn't enabled.
+	pub fn release(&mut self, checkpoint: Checkpoint<Sp::Coordinate>) {
+		self.checkpoints.retain(|(id, _)| *id != checkpoint.id);
+		if !self.checkpoints.is_empty() {
+			return;
+		}
+		if self.max_backtracks == 0 {
+			self.journal = None;
+			self.decisions.clear();
+			self.restrictions.clear();
+			return;
+		}
+		let Some(journal) = &mut self.journal else {
+			return;
+		};
+		// backtracking never goes back further than the first decision
+		let start = self.decisions.first().map_or(journal.len(), |decision| decision.journal_len);
+		journal.drain(..start);
+		for decision in self.decisions.iter_mut() {
+			decision.journal_len -= start;
+		}
+		self.restrictions.retain(|restriction| restriction.journal_len >= start);
+		for restriction in self.restrictions.iter_mut() {
+			restriction.journal_len -= start;
+		}
+	}
+	
+	fn modify(&mut self, coord: Sp::Coordinate, states: &St, apply: fn(&mut St, &St)) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		// restrictions have no way to report being interrupted, so they always
+		// run to completion
+		let cancellation = self.cancellation.take();
+		let deadline = self.deadline.take();
+		let result = self.try_modify(coord, states, apply);
+		self.cancellation = cancellation;
+		self.deadline = deadline;
+		result
+	}
+	
+	fn try_modify(&mut self, coord: Sp::Coordinate, states: &St, apply: fn(&mut St, &St)) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		if let Some(contradiction) = &self.contradiction {
+			return Err(contradiction.clone());
+		}
+		let ready = if !self.initialized {
+			self.initialized = true;
+			self.initialize()
+		} else if self.stopped {
+			self.resume()
+		} else {
+			Ok(())
+		};
+		if let Err(contradiction) = ready {
+			self.contradiction = Some(contradiction.clone());
+			return Err(contradiction);
+		}
+		// keep a journal while propagating, even without backtracking, so that
+		// the space can be put back if the restriction fails
+		let journaling = self.journal.is_some();
+		if !journaling {
+			self.journal = Some(Vec::new());
+		}
+		let journal_len = self.record(coord);
+		let before = self.space[coord].clone();
+		apply(&mut self.space[coord], states);
+		let result = self.propogate_modified(coord, before);
+		match &result {
+			Err(_) => {
+				self.undo(journal_len);
+				self.reset_caches();
+			},
+			// kept even if the cell already had no other states, as
+			// backtracking may give them back
+			Ok(()) => if journaling {
+				self.restrictions.push(Restriction {
+					coordinate: coord,
+					states: states.clone(),
+					apply,
+					journal_len,
+				});
+			},
+		}
+		if !journaling {
+			self.journal = None;
+		}
+		result
+	}
+	
+	fn propogate_modified(&mut self, coord: Sp::Coordinate, before: St) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		// the cell may have been made final, so check it against its
+		// neighbors here since propagation skips final cells
+		self.gather_neighbor_states(coord);
+		self.rule.collapse(&mut self.space[coord], &self.neighbor_states[..]);
+		self.stats.rule_collapses += 1;
+		if self.space[coord].is_contradiction() {
+			return Err(self.contradiction_at(coord));
+		}
+		if self.space[coord] == before {
+			return Ok(());
+		}
+		self.listener.reduced(coord, &self.space[coord]);
+		self.cell_changed(coord);
+		self.propogate_changes(&[(coord, before)])
+	}
+	
+	/// Propagates the effects of each cell in `changed` losing states, from
+	/// the state paired with it, then enforces the global constraints. Cells
+	/// left waiting by an interrupted propagation are propagated as well.
+	fn propogate_changes(&mut self, changed: &[(Sp::Coordinate, St)]) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		let start = Instant::now();
+		self.stopped = false;
+		let mut result = self.propogate_cells(changed);
+		if result.is_ok() && !self.stopped {
+			result = self.enforce_constraints();
+		}
+		self.stats.propagation_time += start.elapsed();
+		result
+	}
+	
+	/// Carries on with the propagation left over from an interrupted step,
+	/// backtracking if it runs into a contradiction.
+	fn resume(&mut self) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		if let Err(contradiction) = self.propogate_changes(&[]) {
+			self.backtrack(contradiction)?;
+		}
+		Ok(())
+	}
+	
+	/// Applies the restrictions asked for by the global constraints until none
+	/// of them need any more.
+	fn enforce_constraints(&mut self) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		let mut restrictions = Vec::new();
+		// where each constraint's restrictions end
+		let mut ends = Vec::new();
+		while !self.constraints.is_empty() {
+			let mut violation = None;
+			ends.clear();
+			for constraint in &mut self.constraints {
+				if let Err(coord) = constraint.check(self.space, &mut restrictions) {
+					violation = Some(coord);
+					break;
+				}
+				ends.push(restrictions.len());
+			}
+			if let Some(coord) = violation {
+				return Err(self.contradiction_at(coord));
+			}
+			// every restriction was worked out from the cells as they are now,
+			// so one whose cell has since been changed by propagating another
+			// would bring back states which have been removed
+			let checked: Vec<_> = restrictions.iter().map(|(coord, _)| self.space[*coord].clone()).collect();
+			let mut changed = false;
+			let mut constraint = 0;
+			for (i, ((coord, state), checked)) in restrictions.drain(..).zip(checked).enumerate() {
+				while ends[constraint] <= i {
+					constraint += 1;
+				}
+				if self.space[coord] != checked {
+					// the constraint works it out again on the next check
+					self.constraints[constraint].reset();
+					changed = true;
+					continue;
+				}
+				if self.space[coord] == state {
+					continue;
+				}
+				changed = true;
+				self.record(coord);
+				let before = std::mem::replace(&mut self.space[coord], state);
+				if self.space[coord].is_contradiction() {
+					return Err(self.contradiction_at(coord));
+				}
+				self.listener.reduced(coord, &self.space[coord]);
+				self.cell_changed(coord);
+				self.propogate_cells(&[(coord, before)])?;
+				if self.stopped {
+					return Ok(());
+				}
+			}
+			if !changed {
+				break;
+			}
+		}
+		Ok(())
+	}
+	
+	/// Propagates the effects of each cell in `changed` losing states, from
+	/// the state paired with it.
+	fn propogate_cells(&mut self, changed: &[(Sp::Coordinate, St)]) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		if self.propagator.is_some() {
+			return self.run_propagator(changed);
+		}
+		for (coord, _) in changed {
+			self.space.neighbors(*coord, &self.neighbor_directions, &mut self.neighbors);
+			for neighbor_coord in self.neighbors.iter().flatten() {
+				self.to_propogate.push(*neighbor_coord, self.space.index_of(*neighbor_coord));
+			}
+		}
+		self.run_propogation()
+	}
+	
+	fn run_propagator(&mut self, changed: &[(Sp::Coordinate, St)]) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		let Some(propagator) = &mut self.propagator else {
+			return Ok(());
+		};
+		let cancellation = &self.cancellation;
+		let deadline = self.deadline;
+		let mut modified = Vec::new();
+		let result = propagator.propagate(self.space, changed, &mut modified, &|| is_interrupted(cancellation, deadline));
+		self.stats.propagation_steps += modified.len();
+		for (coord, before) in modified {
+			if let Some(journal) = &mut self.journal {
+				journal.push((coord, before));
+			}
+			if !self.space[coord].is_contradiction() {
+				self.listener.reduced(coord, &self.space[coord]);
+				self.cell_changed(coord);
+			}
+		}
+		match result {
+			Ok(finished) => {
+				self.stopped = !finished;
+				Ok(())
+			},
+			Err(coord) => Err(self.contradiction_at(coord)),
+		}
+	}
+	
+	/// Discards anything the heuristic and propagator have kept about cells,
+	/// along with any cells waiting to be propagated, after cells may have
+	/// regained states.
+	fn reset_caches(&mut self) {
+		self.to_propogate.clear();
+		self.stopped = false;
+		self.heuristic.reset();
+		if let Some(propagator) = &mut self.propagator {
+			propagator.reset();
+		}
+		for constraint in &mut self.constraints {
+			constraint.reset();
+		}
+	}
+	
+	fn try_step(&mut self) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		if self.done {
+			return Ok(());
+		}
+		if !self.initialized {
+			self.initialized = true;
+			self.initialize()?;
+		} else if self.stopped {
+			self.resume()?;
+			if !self.stopped && self.stats.observations > 0 {
+				self.listener.progress(self.unresolved_set.len());
+			}
+		}
+		if self.stopped {
+			return Ok(());
+		}
+		let start = Instant::now();
+		let next = self.find_next_to_collapse();
+		self.stats.selection_time += start.elapsed();
+		match next {
+			Some(to_collapse) => {
+				if let Err(contradiction) = self.observe(to_collapse) {
+					self.backtrack(contradiction)?;
+				}
+				if !self.stopped {
+					self.listener.progress(self.unresolved_set.len());
+				}
+			},
+			None => {
+				self.done = true;
+				self.listener.completed(&self.stats);
+			},
+		}
+		Ok(())
+	}
+	
+	fn initialize(&mut self) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		let coordinates = match &self.region {
+			Some(region) => region.iter().copied().collect(),
+			None => self.space.coordinate_list(),
+		};
+		for coord in &coordinates[..] {
+			if self.space[*coord].is_contradiction() {
+				return Err(self.contradiction_at(*coord));
+			}
+			if self.space[*coord].entropy() > 0 {
+				self.unresolved_set.insert(*coord);
+			}
+		}
+		self.reset_caches();
+		// the propagator checks every cell on its first run, so only the rule
+		// needs every cell queued
+		if self.propagator.is_none() {
+			for coord in self.unresolved_set.iter() {
+				self.to_propogate.push(*coord, self.space.index_of(*coord));
+			}
+		}
+		// there are no decisions to undo yet, so this can't be backtracked
+		self.propogate_changes(&[])
+	}
+	
+	fn find_next_to_collapse(&mut self) -> Option<Sp::Coordinate> {
+		let mut context = SelectionContext {
+			space: self.space,
+			rule: self.rule,
+			unresolved: &self.unresolved_set,
+			neighbor_directions: &self.neighbor_directions,
+			last_observed: self.last_observed,
+			rng: &mut self.rng,
+		};
+		self.heuristic.select(&mut context)
+	}
+	
+	/// Keeps the unresolved set and the heuristic up to date after the cell
+	/// at `coord` loses possible states.
+	fn cell_changed(&mut self, coord: Sp::Coordinate) {
+		if self.space[coord].entropy() == 0 {
+			self.unresolved_set.remove(&coord);
+		}
+		for constraint in &mut self.constraints {
+			constraint.update(coord, self.space);
+		}
+		let mut context = SelectionContext {
+			space: self.space,
+			rule: self.rule,
+			unresolved: &self.unresolved_set,
+			neighbor_directions: &self.neighbor_directions,
+			last_observed: self.last_observed,
+			rng: &mut self.rng,
+		};
+		self.heuristic.update(coord, &mut context);
+	}
+	
+	fn observe(&mut self, to_collapse: Sp::Coordinate) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		self.gather_neighbor_states(to_collapse);
+		let journal_len = self.record(to_collapse);
+		let before = self.space[to_collapse].clone();
+		self.last_observed = Some(to_collapse);
+		let start = Instant::now();
+		for constraint in &mut self.constraints {
+			if let Some(states) = constraint.bias(to_collapse, self.space, &mut self.rng) {
+				self.space[to_collapse] = states;
+				break;
+			}
+		}
+		self.rule.observe(&mut self.space[to_collapse], &self.neighbor_states[..], &mut self.rng);
+		self.stats.observation_time += start.elapsed();
+		self.stats.observations += 1;
+		if self.journal.is_some() {
+			self.decisions.push(Decision {
+				coordinate: to_collapse,
+				observed: self.space[to_collapse].clone(),
+				journal_len,
+			});
+		}
+		if self.space[to_collapse].is_contradiction() {
+			return Err(self.contradiction_at(to_collapse));
+		}
+		self.listener.observed(to_collapse, &self.space[to_collapse]);
+		self.cell_changed(to_collapse);
+		self.propogate_changes(&[(to_collapse, before)])
+	}
+	
+	/// Undoes observations until the space is free of contradictions again,
+	/// banning each undone observation from being made again.
+	fn backtrack(&mut self, mut contradiction: Contradiction<Sp::Coordinate, St>) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		loop {
+			if self.stats.backtracks >= self.max_backtracks {
+				return Err(contradiction);
+			}
+			let Some(decision) = self.decisions.pop() else {
+				return Err(contradiction);
+			};
+			self.stats.backtracks += 1;
+			self.undo(decision.journal_len);
+			self.reset_caches();
+			self.listener.backtracked(decision.coordinate, &decision.observed);
+			self.last_observed = Some(decision.coordinate);
+			
+			let coordinate = decision.coordinate;
+			self.record(coordinate);
+			let before = self.space[coordinate].clone();
+			let mut changed = Vec::new();
+			// if the rule can't exclude the observed state, the cell is left to
+			// be observed again
+			if self.rule.ban(&mut self.space[coordinate], &decision.observed) {
+				if self.space[coordinate].is_contradiction() {
+					contradiction = self.contradiction_at(coordinate);
+					continue;
+				}
+				self.cell_changed(coordinate);
+				changed.push((coordinate, before));
+			}
+			if let Err(next_contradiction) = self.reapply_restrictions(decision.journal_len, &mut changed) {
+				contradiction = next_contradiction;
+				continue;
+			}
+			if changed.is_empty() {
+				return Ok(());
+			}
+			match self.propogate_changes(&changed) {
+				Ok(()) => return Ok(()),
+				Err(next_contradiction) => contradiction = next_contradiction,
+			}
+		}
+	}
+	
+	/// Makes the restrictions undone by going back to when the journal was
+	/// `journal_len` long again, adding each cell they change to `changed`
+	/// unless it's there already.
+	fn reapply_restrictions(&mut self, journal_len: usize, changed: &mut Vec<(Sp::Coordinate, St)>) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		for i in 0 .. self.restrictions.len() {
+			if self.restrictions[i].journal_len < journal_len {
+				continue;
+			}
+			let coord = self.restrictions[i].coordinate;
+			self.restrictions[i].journal_len = self.record(coord);
+			let before = self.space[coord].clone();
+			let restriction = &self.restrictions[i];
+			(restriction.apply)(&mut self.space[coord], &restriction.states);
+			self.gather_neighbor_states(coord);
+			self.rule.collapse(&mut self.space[coord], &self.neighbor_states[..]);
+			self.stats.rule_collapses += 1;
+			if self.space[coord].is_contradiction() {
+				return Err(self.contradiction_at(coord));
+			}
+			if self.space[coord] == before {
+				continue;
+			}
+			self.listener.reduced(coord, &self.space[coord]);
+			self.cell_changed(coord);
+			if !changed.iter().any(|(changed_coord, _)| *changed_coord == coord) {
+				changed.push((coord, before));
+			}
+		}
+		Ok(())
+	}
+	
+	/// Saves the state of a cell to the journal before modifying it, if
+	/// backtracking is enabled or a checkpoint has been taken. Returns the length of the journal beforehand.
+	fn record(&mut self, coord: Sp::Coordinate) -> usize {
+		match &mut self.journal {
+			Some(journal) => {
+				let journal_len = journal.len();
+				journal.push((coord, self.space[coord].clone()));
+				journal_len
+			},
+			None => 0
+		}
+	}
+	
+	/// Restores every cell modified since the journal was `journal_len` long.
+	fn undo(&mut self, journal_len: usize) {
+		self.checkpoints.retain(|(_, checkpoint_len)| *checkpoint_len <= journal_len);
+		if let Some(journal) = &mut self.journal {
+			while journal.len() > journal_len {
+				let (coord, state) = journal.pop().unwrap();
+				let in_region = self.region.as_ref().map_or(true, |region| region.contains(&coord));
+				if state.entropy() > 0 && in_region {
+					self.unresolved_set.insert(coord);
+				}
+				self.space[coord] = state;
+			}
+		}
+	}
+	
+	fn gather_neighbor_states(&mut self, coord: Sp::Coordinate) {
+		self.space.neighbors(coord, &self.neighbor_directions, &mut self.neighbors);
+		for i in 0 .. self.neighbor_directions.len() {
+			self.neighbor_states[i] = self.neighbors[i].map(|coord| self.space[coord].clone());
+		}
+	}
+	
+	fn contradiction_at(&mut self, coordinate: Sp::Coordinate) -> Contradiction<Sp::Coordinate, St> {
+		self.stats.contradictions += 1;
+		self.gather_neighbor_states(coordinate);
+		let contradiction = Contradiction {
+			coordinate,
+			neighbors: self.neighbor_states.clone(),
+		};
+		self.listener.contradiction(&contradiction);
+		contradiction
+	}
+	
+	#[cfg(not(feature = "parallel"))]
+	fn run_propogation(&mut self) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		// counted from the start of each call, so that every call gets
+		// somewhere however little time it's given
+		let mut propagated = 0;
+		while !self.to_propogate.is_empty() {
+			if propagated == INTERRUPT_CHECK_INTERVAL {
+				if self.is_interrupted() {
+					// the rest of the queue is kept for the next step
+					self.stopped = true;
+					return Ok(());
+				}
+				propagated = 0;
+			}
+			propagated += 1;
+			self.stats.peak_queue_length = self.stats.peak_queue_length.max(self.to_propogate.len());
+			let propogating = self.to_propogate.pop().unwrap();
+			self.stats.propagation_steps += 1;
+			let entropy_before = self.space[propogating].entropy();
+			
+			if entropy_before != 0 {
+				self.gather_neighbor_states(propogating);
+				let state_before = self.journal.as_ref().map(|_| self.space[propogating].clone());
+				self.rule.collapse(&mut self.space[propogating], &self.neighbor_states[..]);
+				self.stats.rule_collapses += 1;
+				if let (Some(journal), Some(state_before)) = (&mut self.journal, state_before) {
+					if state_before != self.space[propogating] {
+						journal.push((propogating, state_before));
+					}
+				}
+				if self.space[propogating].is_contradiction() {
+					return Err(self.contradiction_at(propogating));
+				}
+				let entropy_after = self.space[propogating].entropy();
+				
+				if entropy_after < entropy_before {
+					self.listener.reduced(propogating, &self.space[propogating]);
+					self.cell_changed(propogating);
+					for neighbor in self.neighbors.iter().flatten() {
+						if self.space[*neighbor].entropy() != 0 {
+							self.to_propogate.push(*neighbor, self.space.index_of(*neighbor));
+						}
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+	
+	/// Propagates in batches, collapsing every cell in the queue in parallel
+	/// against the space as it was at the start of the batch, then applying the
+	/// results in coordinate order so that collapse stays deterministic.
+	#[cfg(feature = "parallel")]
+	fn run_propogation(&mut self) -> Result<(), Contradiction<Sp::Coordinate, St>> {
+		use rayon::prelude::*;
+		
+		let mut batch = BTreeSet::new();
+		while let Some(coord) = self.to_propogate.pop() {
+			if self.space[coord].entropy() != 0 {
+				batch.insert(coord);
+			}
+		}
+		while !batch.is_empty() {
+			self.stats.propagation_steps += batch.len();
+			self.stats.rule_collapses += batch.len();
+			self.stats.peak_queue_length = self.stats.peak_queue_length.max(batch.len());
+			let space = &*self.space;
+			let rule = self.rule;
+			let neighbor_directions = &self.neighbor_directions[..];
+			let collapsed: Vec<(Sp::Coordinate, St)> = batch.into_iter()
+				.collect::<Vec<_>>()
+				.into_par_iter()
+				.map_init(|| (vec![None; neighbor_directions.len()], vec![None; neighbor_directions.len()]), |(neighbors, neighbor_states), coord| {
+					space.neighbors(coord, neighbor_directions, neighbors);
+					for (neighbor_state, neighbor) in neighbor_states.iter_mut().zip(neighbors.iter()) {
+						*neighbor_state = neighbor.map(|neighbor| space[neighbor].clone());
+					}
+					let mut state = space[coord].clone();
+					rule.collapse(&mut state, &neighbor_states[..]);
+					(coord, state)
+				})
+				.collect();
+			
+			batch = BTreeSet::new();
+			let mut changed = BTreeSet::new();
+			for (coord, state) in collapsed {
+				if state == self.space[coord] {
+					continue;
+				}
+				let entropy_before = self.space[coord].entropy();
+				let state_before = std::mem::replace(&mut self.space[coord], state);
+				if let Some(journal) = &mut self.journal {
+					journal.push((coord, state_before));
+				}
+				if self.space[coord].is_contradiction() {
+					return Err(self.contradiction_at(coord));
+				}
+				if self.space[coord].entropy() < entropy_before {
+					self.listener.reduced(coord, &self.space[coord]);
+					self.cell_changed(coord);
+					changed.insert(coord);
+					self.space.neighbors(coord, &self.neighbor_directions, &mut self.neighbors);
+					for neighbor in self.neighbors.iter().flatten() {
+						if changed.contains(neighbor) {
+							// both cells were collapsed against the other's old
+							// state, so check them again even if they're final
+							batch.insert(*neighbor);
+							batch.insert(coord);
+						} else if self.space[*neighbor].entropy() != 0 {
+							batch.insert(*neighbor);
+						}
+					}
+				}
+			}
+			// checked after each batch, so that every call gets somewhere however
+			// little time it's given
+			if !batch.is_empty() && self.is_interrupted() {
+				// the next batch is kept for the next step
+				for coord in batch {
+					self.to_propogate.push(coord, self.space.index_of(coord));
+				}
+				self.stopped = true;
+				return Ok(());
+			}
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,308 @@
+//! Built-in [GlobalConstraint] implementations
+
+use std::collections::{BTreeSet, HashMap};
+
+use rand::{Rng, RngCore};
+
+use crate::{State, SetState, Space, GlobalConstraint};
+
+/// Limits how many cells of the space can collapse to a final state, such as
+/// "exactly one boss room" or "at most 3 shops".
+/// 
+/// Once the maximum is reached, the state is removed from every other cell.
+/// While the minimum hasn't been reached, observations are biased towards the
+/// state so that it doesn't run out of room, and when only the minimum number
+/// of cells can still become the state, they're all forced to it.
+/// 
+/// A space without any cells is left as it is, whatever the minimum.
+pub struct CountConstraint<St, C> {
+	state: St,
+	min: usize,
+	max: usize,
+	built: bool,
+	/// Cells which still have the state
+	possible: BTreeSet<C>,
+	/// Cells which have collapsed to the state
+	fixed: BTreeSet<C>,
+	last_lost: Option<C>,
+}
+
+impl<St, C: Ord> CountConstraint<St, C> {
+	/// Create a new CountConstraint
+	/// 
+	/// * `state` - the final state to count
+	/// * `min` - least number of cells which must collapse to `state`
+	/// * `max` - most cells which can collapse to `state`
+	pub fn new(state: St, min: usize, max: usize) -> Self {
+		assert!(min <= max);
+		Self {
+			state,
+			min,
+			max,
+			built: false,
+			possible: BTreeSet::new(),
+			fixed: BTreeSet::new(),
+			last_lost: None,
+		}
+	}
+	
+	/// Exactly `count` cells must collapse to `state`
+	pub fn exactly(state: St, count: usize) -> Self {
+		Self::new(state, count, count)
+	}
+	
+	/// At least `count` cells must collapse to `state`
+	pub fn at_least(state: St, count: usize) -> Self {
+		Self::new(state, count, usize::MAX)
+	}
+	
+	/// At most `count` cells can collapse to `state`
+	pub fn at_most(state: St, count: usize) -> Self {
+		Self::new(state, 0, count)
+	}
+}
+
+impl<St: State + SetState, Sp: Space<St>> GlobalConstraint<St, Sp> for CountConstraint<St, Sp::Coordinate> {
+	fn update(&mut self, coord: Sp::Coordinate, space: &Sp) {
+		if !self.built {
+			return;
+		}
+		if !space[coord].has_any_of(&self.state) {
+			if self.possible.remove(&coord) {
+				self.last_lost = Some(coord);
+			}
+			self.fixed.remove(&coord);
+		} else if space[coord] == self.state {
+			self.fixed.insert(coord);
+		}
+	}
+	
+	fn check(&mut self, space: &Sp, restrictions: &mut Vec<(Sp::Coordinate, St)>) -> Result<(), Sp::Coordinate> {
+		if !self.built {
+			self.possible.clear();
+			self.fixed.clear();
+			for coord in space.coordinate_list().iter() {
+				if space[*coord].has_any_of(&self.state) {
+					self.possible.insert(*coord);
+					if space[*coord] == self.state {
+						self.fixed.insert(*coord);
+					}
+				}
+			}
+			self.built = true;
+		}
+		if self.fixed.len() > self.max {
+			return Err(*self.fixed.last().unwrap());
+		}
+		if self.possible.len() < self.min {
+			// an empty space has no cell to report, and nothing to collapse
+			let coord = self.last_lost.or_else(|| space.coordinate_list().first().copied());
+			return coord.map_or(Ok(()), Err);
+		}
+		if self.possible.len() == self.fixed.len() {
+			return Ok(());
+		}
+		if self.fixed.len() == self.max {
+			for coord in self.possible.difference(&self.fixed) {
+				let mut state = space[*coord].clone();
+				state.clear_states(&self.state);
+				restrictions.push((*coord, state));
+			}
+		} else if self.possible.len() == self.min {
+			for coord in self.possible.difference(&self.fixed) {
+				restrictions.push((*coord, self.state.clone()));
+			}
+		}
+		Ok(())
+	}
+	
+	fn bias(&mut self, coord: Sp::Coordinate, space: &Sp, rng: &mut dyn RngCore) -> Option<St> {
+		if !self.built || self.fixed.len() >= self.min || !space[coord].has_any_of(&self.state) {
+			return None;
+		}
+		// pick the state more often than the remaining candidates strictly
+		// need, as picking it can rule it out of neighboring cells
+		let needed = (self.min - self.fixed.len()) as f64;
+		let candidates = (self.possible.len() - self.fixed.len()) as f64;
+		if rng.gen_bool((2.0 * needed / candidates).min(1.0)) {
+			Some(self.state.clone())
+		} else {
+			None
+		}
+	}
+	
+	fn reset(&mut self) {
+		self.built = false;
+		self.last_lost = None;
+	}
+}
+
+/// Keeps every cell which collapses to one of the `passable` states, along
+/// with a set of required coordinates, connected as a single region. This can
+/// be used to make all floor tiles walkable from each other, or to make a
+/// road link two pinned points.
+/// 
+/// Cells which every route between passable cells goes through are forced to
+/// be passable, and cells which can no longer reach the region lose their
+/// passable states.
+/// 
+/// Each check walks every cell which could be passable, so this is best
+/// suited to smaller spaces.
+pub struct ConnectivityConstraint<St, C, D> {
+	passable: St,
+	directions: Box<[D]>,
+	required: Vec<C>,
+	built: bool,
+	dirty: bool,
+	coordinates: Box<[C]>,
+	indices: HashMap<C, usize>,
+	neighbors: Vec<Vec<usize>>,
+}
+
+impl<St, C, D: Clone> ConnectivityConstraint<St, C, D> {
+	/// Create a new ConnectivityConstraint
+	/// 
+	/// * `passable` - the states which cells can be connected through
+	/// * `directions` - neighbor offsets which count as connected
+	pub fn new(passable: St, directions: &[D]) -> Self {
+		Self {
+			passable,
+			directions: directions.to_vec().into_boxed_slice(),
+			required: Vec::new(),
+			built: false,
+			dirty: true,
+			coordinates: Box::new([]),
+			indices: HashMap::new(),
+			neighbors: Vec::new(),
+		}
+	}
+	
+	/// Requires the cells at `coords` to be passable, and part of the
+	/// connected region. Coordinates outside the space can never be, so
+	/// collapse fails with a contradiction at the first of them.
+	pub fn with_required(mut self, coords: &[C]) -> Self where C: Clone {
+		self.required.extend_from_slice(coords);
+		self
+	}
+}
+
+impl<St: State + SetState, Sp: Space<St>> GlobalConstraint<St, Sp> for ConnectivityConstraint<St, Sp::Coordinate, Sp::CoordinateDelta> {
+	fn update(&mut self, coord: Sp::Coordinate, space: &Sp) {
+		// only cells which can no longer be passable, or can only be passable,
+		// change anything
+		let mut impassable = space[coord].clone();
+		impassable.clear_states(&self.passable);
+		if impassable.is_empty() || !space[coord].has_any_of(&self.passable) {
+			self.dirty = true;
+		}
+	}
+	
+	fn check(&mut self, space: &Sp, restrictions: &mut Vec<(Sp::Coordinate, St)>) -> Result<(), Sp::Coordinate> {
+		if !self.built {
+			self.coordinates = space.coordinate_list();
+			self.indices = self.coordinates.iter().enumerate().map(|(i, coord)| (*coord, i)).collect();
+			let mut neighbors = vec![None; self.directions.len()];
+			self.neighbors = self.coordinates.iter().map(|coord| {
+				space.neighbors(*coord, &self.directions, &mut neighbors);
+				neighbors.iter().flatten().map(|neighbor| self.indices[neighbor]).collect()
+			}).collect();
+			self.built = true;
+		}
+		if !self.dirty {
+			return Ok(());
+		}
+		self.dirty = false;
+		
+		let count = self.coordinates.len();
+		let mut possible = vec![false; count];
+		let mut relevant = vec![false; count];
+		for (i, coord) in self.coordinates.iter().enumerate() {
+			possible[i] = space[*coord].has_any_of(&self.passable);
+			let mut impassable = space[*coord].clone();
+			impassable.clear_states(&self.passable);
+			relevant[i] = possible[i] && impassable.is_empty();
+		}
+		for coord in self.required.iter() {
+			let Some(&i) = self.indices.get(coord) else {
+				return Err(*coord);
+			};
+			if !possible[i] {
+				return Err(*coord);
+			}
+			if !relevant[i] {
+				let mut state = space[*coord].clone();
+				state.retain_states(&self.passable);
+				restrictions.push((*coord, state));
+				relevant[i] = true;
+			}
+		}
+		let Some(root) = relevant.iter().position(|relevant| *relevant) else {
+			return Ok(());
+		};
+		
+		// depth first search for cut vertices of the possibly passable cells,
+		// counting how many relevant cells are below each one
+		let mut discovered = vec![usize::MAX; count];
+		let mut low = vec![0; count];
+		let mut relevant_below = vec![0usize; count];
+		let mut cut = vec![false; count];
+		let mut time = 0;
+		let mut stack = vec![(root, 0)];
+		discovered[root] = time;
+		low[root] = time;
+		relevant_below[root] = 1;
+		while let Some((cell, next)) = stack.last_mut() {
+			let cell = *cell;
+			if let Some(neighbor) = self.neighbors[cell].get(*next).copied() {
+				*next += 1;
+				if !possible[neighbor] {
+					continue;
+				}
+				if discovered[neighbor] == usize::MAX {
+					time += 1;
+					discovered[neighbor] = time;
+					low[neighbor] = time;
+					relevant_below[neighbor] = relevant[neighbor] as usize;
+					stack.push((neighbor, 0));
+				} else {
+					low[cell] = low[cell].min(discovered[neighbor]);
+				}
+			} else {
+				stack.pop();
+				if let Some((parent, _)) = stack.last() {
+					let parent = *parent;
+					low[parent] = low[parent].min(low[cell]);
+					relevant_below[parent] += relevant_below[cell];
+					// the root is relevant, so cutting off a relevant cell
+					// splits the region
+					if low[cell] >= discovered[parent] && relevant_below[cell] > 0 {
+						cut[parent] = true;
+					}
+				}
+			}
+		}
+		
+		for i in 0 .. count {
+			let coord = self.coordinates[i];
+			if discovered[i] == usize::MAX {
+				if relevant[i] {
+					return Err(coord);
+				}
+				if possible[i] {
+					let mut state = space[coord].clone();
+					state.clear_states(&self.passable);
+					restrictions.push((coord, state));
+				}
+			} else if cut[i] && !relevant[i] {
+				let mut state = space[coord].clone();
+				state.retain_states(&self.passable);
+				restrictions.push((coord, state));
+			}
+		}
+		Ok(())
+	}
+	
+	fn reset(&mut self) {
+		self.dirty = true;
+	}
+}
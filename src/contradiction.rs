@@ -0,0 +1,64 @@
+use std::{fmt::{self, Debug, Display}, error::Error};
+
+/// Returned when a cell runs out of possible states during collapse, meaning
+/// the space cannot be resolved from its current state with the given rule.
+/// 
+/// * `C` - The coordinate type of the space
+/// * `S` - The cell state type
+#[derive(Clone, PartialEq, Debug)]
+pub struct Contradiction<C, S> {
+	/// Coordinate of the cell which ran out of possible states
+	pub coordinate: C,
+	/// States of the cell's neighbors at the time of the contradiction, in the
+	/// order given by the rule's `neighbor_offsets()`.
+	pub neighbors: Box<[Option<S>]>,
+}
+
+impl<C: Debug, S> Display for Contradiction<C, S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "contradiction at cell {:?}", self.coordinate)
+	}
+}
+
+impl<C: Debug, S: Debug> Error for Contradiction<C, S> {}
+
+/// Returned by [crate::Collapser::pin] when a cell can't be pinned
+#[derive(Clone, PartialEq, Debug)]
+pub enum PinError<C, S> {
+	/// The given state has more or less than one possible state, so it isn't
+	/// a final state the cell can be pinned to
+	NotFinal,
+	/// Pinning the cell left some cell without any possible state
+	Contradiction(Contradiction<C, S>),
+}
+
+impl<C, S> From<Contradiction<C, S>> for PinError<C, S> {
+	fn from(contradiction: Contradiction<C, S>) -> Self {
+		Self::Contradiction(contradiction)
+	}
+}
+
+impl<C: Debug, S> Display for PinError<C, S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::NotFinal => write!(f, "cannot pin a cell to a state which isn't final"),
+			Self::Contradiction(contradiction) => Display::fmt(contradiction, f),
+		}
+	}
+}
+
+impl<C: Debug, S: Debug> Error for PinError<C, S> {}
+
+/// Returned by [crate::Collapser::rollback] when the checkpoint can no longer
+/// be rolled back to, because it has been released, or collapse has gone back
+/// past it since.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StaleCheckpoint;
+
+impl Display for StaleCheckpoint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "checkpoint can no longer be rolled back to")
+	}
+}
+
+impl Error for StaleCheckpoint {}
@@ -0,0 +1,182 @@
+use std::ops::{BitOr, BitAnd, BitXor};
+
+use crate::{SetState, State};
+
+/// The set of final states which [DynBitsetState]s are made from, for when the
+/// number of final states is only known at runtime, such as when tiles are
+/// loaded from data files.
+#[derive(PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub struct DynBitsetUniverse {
+	final_state_count: u32,
+}
+
+impl DynBitsetUniverse {
+	/// Create a new DynBitsetUniverse
+	/// 
+	/// * `final_state_count` - the total number of final (fully collapsed) states
+	pub fn new(final_state_count: u32) -> Self {
+		assert!(final_state_count >= 1);
+		Self {
+			final_state_count,
+		}
+	}
+	
+	/// The total number of final states
+	pub fn final_state_count(&self) -> u32 {
+		self.final_state_count
+	}
+	
+	fn words(&self) -> usize {
+		self.final_state_count.div_ceil(64) as usize
+	}
+	
+	/// Creates a state with no possible states
+	pub fn empty(&self) -> DynBitsetState {
+		DynBitsetState(vec![0; self.words()].into_boxed_slice())
+	}
+	
+	/// Creates the `n`th unique state
+	pub fn state(&self, n: u32) -> DynBitsetState {
+		self.with_states(&[n])
+	}
+	
+	/// Creates a state representing the states numbered by members of `states`
+	pub fn with_states(&self, states: &[u32]) -> DynBitsetState {
+		let mut state = self.empty();
+		for i in states {
+			assert!(*i < self.final_state_count);
+			state.0[(*i / 64) as usize] |= 1u64 << (*i % 64);
+		}
+		state
+	}
+	
+	/// Creates a state with every final state possible, in place of
+	/// [crate::AllState::all]
+	pub fn all(&self) -> DynBitsetState {
+		let mut state = self.empty();
+		for (i, word) in state.0.iter_mut().enumerate() {
+			let remaining = self.final_state_count - i as u32 * 64;
+			*word = if remaining >= 64 {
+				0xFFFF_FFFF_FFFF_FFFF
+			} else {
+				(1u64 << remaining) - 1
+			};
+		}
+		state
+	}
+}
+
+/// A state type which uses a heap allocated bitset to describe any number of
+/// possible final states, fixed by the [DynBitsetUniverse] it was made from.
+/// States from different universes shouldn't be mixed.
+#[derive(PartialEq, Eq, Clone, Hash, Debug)]
+pub struct DynBitsetState(Box<[u64]>);
+
+impl DynBitsetState {
+	fn zip_with(mut self, rhs: &Self, op: impl Fn(u64, u64) -> u64) -> Self {
+		debug_assert_eq!(self.0.len(), rhs.0.len());
+		for (word, rhs_word) in self.0.iter_mut().zip(rhs.0.iter()) {
+			*word = op(*word, *rhs_word);
+		}
+		self
+	}
+	
+	fn zip_in_place(&mut self, rhs: &Self, op: impl Fn(u64, u64) -> u64) {
+		debug_assert_eq!(self.0.len(), rhs.0.len());
+		for (word, rhs_word) in self.0.iter_mut().zip(rhs.0.iter()) {
+			*word = op(*word, *rhs_word);
+		}
+	}
+}
+
+impl State for DynBitsetState {
+	fn entropy(&self) -> u32 {
+		self.0.iter().map(|word| word.count_ones()).sum::<u32>().saturating_sub(1)
+	}
+	
+	fn is_contradiction(&self) -> bool {
+		self.is_empty()
+	}
+}
+
+impl SetState for DynBitsetState {
+	fn has_any_of(&self, states: &Self) -> bool {
+		self.0.iter().zip(states.0.iter()).any(|(word, states_word)| word & states_word != 0)
+	}
+	
+	fn clear_states(&mut self, states: &Self) {
+		self.zip_in_place(states, |word, states_word| word & !states_word);
+	}
+	
+	fn retain_states(&mut self, states: &Self) {
+		self.zip_in_place(states, |word, states_word| word & states_word);
+	}
+	
+	fn set_states(&mut self, states: &Self) {
+		self.zip_in_place(states, |word, states_word| word | states_word);
+	}
+	
+	fn collect_final_states(&self, states: &mut Vec<Self>) {
+		for (i, word) in self.0.iter().enumerate() {
+			for bit in 0 .. 64 {
+				if word & (1u64 << bit) != 0 {
+					let mut state = DynBitsetState(vec![0; self.0.len()].into_boxed_slice());
+					state.0[i] = 1u64 << bit;
+					states.push(state);
+				}
+			}
+		}
+	}
+	
+	fn is_empty(&self) -> bool {
+		self.0.iter().all(|word| *word == 0)
+	}
+}
+
+impl BitOr for DynBitsetState {
+	type Output = Self;
+	
+	fn bitor(self, rhs: Self) -> Self::Output {
+		self.zip_with(&rhs, |a, b| a | b)
+	}
+}
+
+impl BitOr for &DynBitsetState {
+	type Output = DynBitsetState;
+	
+	fn bitor(self, rhs: Self) -> Self::Output {
+		self.clone().zip_with(rhs, |a, b| a | b)
+	}
+}
+
+impl BitAnd for DynBitsetState {
+	type Output = Self;
+	
+	fn bitand(self, rhs: Self) -> Self::Output {
+		self.zip_with(&rhs, |a, b| a & b)
+	}
+}
+
+impl BitAnd for &DynBitsetState {
+	type Output = DynBitsetState;
+	
+	fn bitand(self, rhs: Self) -> Self::Output {
+		self.clone().zip_with(rhs, |a, b| a & b)
+	}
+}
+
+impl BitXor for DynBitsetState {
+	type Output = Self;
+	
+	fn bitxor(self, rhs: Self) -> Self::Output {
+		self.zip_with(&rhs, |a, b| a ^ b)
+	}
+}
+
+impl BitXor for &DynBitsetState {
+	type Output = DynBitsetState;
+	
+	fn bitxor(self, rhs: Self) -> Self::Output {
+		self.clone().zip_with(rhs, |a, b| a ^ b)
+	}
+}
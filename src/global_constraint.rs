@@ -0,0 +1,35 @@
+use rand::RngCore;
+
+use crate::{State, Space};
+
+/// A constraint over the whole space, checked alongside the local
+/// [crate::CollapseRule] during collapse. See [crate::constraints] for the
+/// built-in constraints.
+pub trait GlobalConstraint<St: State, Sp: Space<St>> {
+	/// Called whenever the cell at `coord` loses possible states, so that
+	/// constraints can keep track of the space without scanning all of it.
+	fn update(&mut self, coord: Sp::Coordinate, space: &Sp) {
+		let _ = (coord, space);
+	}
+	/// Checks the constraint once changes have been propagated. Cells which
+	/// need to lose states to keep the constraint satisfiable are added to
+	/// `restrictions` along with their new state, which will be applied and
+	/// propagated before checking again. Restrictions for cells which are
+	/// changed by propagating an earlier one are dropped instead, and the
+	/// constraint is reset so that they can be worked out again.
+	/// 
+	/// Returns the coordinate of a cell involved if the constraint can no
+	/// longer be satisfied.
+	fn check(&mut self, space: &Sp, restrictions: &mut Vec<(Sp::Coordinate, St)>) -> Result<(), Sp::Coordinate>;
+	/// Called when collapse starts, and whenever cells may have regained
+	/// states (such as after backtracking), so that anything kept between
+	/// checks can be discarded.
+	fn reset(&mut self) {}
+	/// Called before the cell at `coord` is observed, to steer the
+	/// observation towards the returned states. These must be a non-empty
+	/// subset of the cell's current state.
+	fn bias(&mut self, coord: Sp::Coordinate, space: &Sp, rng: &mut dyn RngCore) -> Option<St> {
+		let _ = (coord, space, rng);
+		None
+	}
+}
@@ -1,16 +1,20 @@
 use std::{collections::HashSet, hash::Hash};
 
-use crate::{State, SetState};
+use crate::{State, SetState, ParallelBounds};
 
 /// A state type which represents possible states with a hash set.
 /// 
+/// Final states are collected in sorted order, rather than the set's own
+/// randomly keyed order, so that collapse with a seeded rng can be reproduced
+/// on any platform. This is why `T` has to be [Ord] to be used as a
+/// [SetState].
+/// 
 /// * `T` - The underlying unique state identifier
-#[derive(Clone, PartialEq)]
-struct HashsetState<T: Eq + Hash> {
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HashsetState<T: Eq + Hash> {
 	hashset: HashSet<T>
 }
 
-#[allow(unused)]
 impl<T: Eq + Hash + Clone> HashsetState<T> {
 	/// Creates a new HashsetState with just the final state `state` inside
 	pub fn new_final(state: &T) -> Self {
@@ -29,15 +33,47 @@ impl<T: Eq + Hash + Clone> HashsetState<T> {
 			hashset
 		}
 	}
+	
+	/// Creates a new HashsetState with every state in `universe` inside, in
+	/// place of [crate::AllState::all]. Use with
+	/// [crate::set_rule::SetCollapseRuleBuilder::build_from].
+	pub fn all(universe: &[T]) -> Self {
+		Self::new(universe)
+	}
+}
+
+impl<T: Eq + Hash> HashsetState<T> {
+	/// Iterates over the states inside, in an order which differs between
+	/// sets
+	pub fn iter(&self) -> impl Iterator<Item = &T> {
+		self.hashset.iter()
+	}
+	
+	/// Checks if `state` is inside
+	pub fn contains(&self, state: &T) -> bool {
+		self.hashset.contains(state)
+	}
+}
+
+impl<T: Eq + Hash> From<HashSet<T>> for HashsetState<T> {
+	fn from(hashset: HashSet<T>) -> Self {
+		Self {
+			hashset
+		}
+	}
 }
 
-impl<T: Clone + Eq + Hash> State for HashsetState<T> {
+impl<T: Clone + Eq + Hash + ParallelBounds> State for HashsetState<T> {
     fn entropy(&self) -> u32 {
-        self.hashset.len() as u32 - 1
+        (self.hashset.len() as u32).saturating_sub(1)
     }
+	
+	fn is_contradiction(&self) -> bool {
+		self.hashset.is_empty()
+	}
 }
 
-impl <T: Clone + Eq + Hash> SetState for HashsetState<T> {
+impl<T: Clone + Ord + Hash + ParallelBounds> SetState for HashsetState<T> {
 	fn has_any_of(&self, states: &Self) -> bool {
 		!self.hashset.is_disjoint(&states.hashset)
 	}
@@ -46,6 +82,10 @@ impl <T: Clone + Eq + Hash> SetState for HashsetState<T> {
         self.hashset.retain(|x| !states.hashset.contains(x));
     }
 	
+	fn retain_states(&mut self, states: &Self) {
+		self.hashset.retain(|x| states.hashset.contains(x));
+	}
+	
 	fn set_states(&mut self, states: &Self) {
 		for state in states.hashset.iter() {
 			self.hashset.insert(state.clone());
@@ -53,8 +93,12 @@ impl <T: Clone + Eq + Hash> SetState for HashsetState<T> {
 	}
 
     fn collect_final_states(&self, states: &mut Vec<Self>) {
-        self.hashset.iter().map(|x| {
-			states.push(Self::new_final(x));
-		}).for_each(drop);
+		let mut final_states: Vec<_> = self.hashset.iter().collect();
+		final_states.sort();
+		states.extend(final_states.into_iter().map(Self::new_final));
     }
+	
+	fn is_empty(&self) -> bool {
+		self.hashset.is_empty()
+	}
 }
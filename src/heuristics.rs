@@ -0,0 +1,247 @@
+//! Built-in [SelectionHeuristic] implementations
+
+use std::{cmp::Ordering, collections::{BTreeMap, BTreeSet}};
+
+use rand::Rng;
+
+use crate::{State, Space, SelectionHeuristic, SelectionContext};
+
+/// Scale of the random noise added to cell entropies when selecting a cell to
+/// observe. Small enough not to reorder cells with meaningfully different
+/// entropies.
+const ENTROPY_NOISE: f64 = 1e-6;
+
+fn lowest_entropy<'i, St: State + 'i, Sp: Space<St>>(context: &mut SelectionContext<St, Sp>, cells: impl Iterator<Item = &'i Sp::Coordinate>) -> Option<Sp::Coordinate> {
+	let mut lowest_entropy = f64::INFINITY;
+	let mut lowest_entropy_coord = None;
+	for coord in cells {
+		// a little noise breaks ties randomly between equal cells
+		let entropy = context.entropy(*coord) + context.rng.gen::<f64>() * ENTROPY_NOISE;
+		if entropy < lowest_entropy {
+			lowest_entropy = entropy;
+			lowest_entropy_coord = Some(*coord);
+		}
+	}
+	lowest_entropy_coord
+}
+
+/// Cell entropy used as a key into [MinimumEntropy]'s buckets
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct EntropyKey(f64);
+
+impl Ord for EntropyKey {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.0.total_cmp(&other.0)
+	}
+}
+
+impl PartialOrd for EntropyKey {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Eq for EntropyKey {}
+
+/// Observes the cell with the lowest entropy, picking randomly between cells
+/// with equal entropy. This is the default, and usually gives the fewest
+/// contradictions.
+/// 
+/// Cells are kept in buckets by entropy, which are updated as their entropy
+/// drops, so selection doesn't need to look at every unresolved cell. Cells
+/// which have moved to another bucket are only removed from their old bucket
+/// once it's the lowest one.
+pub struct MinimumEntropy<C> {
+	buckets: BTreeMap<EntropyKey, Vec<C>>,
+	built: bool,
+}
+
+impl<C: Ord + Copy> MinimumEntropy<C> {
+	pub fn new() -> Self {
+		Self {
+			buckets: BTreeMap::new(),
+			built: false,
+		}
+	}
+	
+	fn insert<St: State, Sp: Space<St, Coordinate = C>>(&mut self, coord: C, context: &SelectionContext<St, Sp>) {
+		let entropy = EntropyKey(context.entropy(coord));
+		self.buckets.entry(entropy).or_default().push(coord);
+	}
+}
+
+impl<C: Ord + Copy> Default for MinimumEntropy<C> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<St: State, Sp: Space<St>> SelectionHeuristic<St, Sp> for MinimumEntropy<Sp::Coordinate> {
+	fn select(&mut self, context: &mut SelectionContext<St, Sp>) -> Option<Sp::Coordinate> {
+		if !self.built {
+			self.buckets.clear();
+			for coord in context.unresolved.iter() {
+				self.insert(*coord, context);
+			}
+			self.built = true;
+		}
+		while let Some(mut lowest) = self.buckets.first_entry() {
+			let entropy = lowest.key().0;
+			let cells = lowest.get_mut();
+			while !cells.is_empty() {
+				// picking at random from the bucket each time keeps selection
+				// uniform between equal cells, however long they've waited
+				let index = context.rng.gen_range(0..cells.len());
+				let coord = cells[index];
+				if context.is_unresolved(coord) && context.entropy(coord) == entropy {
+					return Some(coord);
+				}
+				cells.swap_remove(index);
+			}
+			lowest.remove();
+		}
+		None
+	}
+	
+	fn update(&mut self, coord: Sp::Coordinate, context: &mut SelectionContext<St, Sp>) {
+		if self.built && context.is_unresolved(coord) {
+			self.insert(coord, context);
+		}
+	}
+	
+	fn reset(&mut self) {
+		self.buckets.clear();
+		self.built = false;
+	}
+}
+
+/// Observes cells in the order given by [Space::coordinate_list], which for
+/// [crate::square_grid::SquareGrid] is left to right, top to bottom.
+#[derive(Clone, Debug)]
+pub struct Scanline<C> {
+	order: Vec<C>,
+	next: usize,
+}
+
+impl<C> Scanline<C> {
+	pub fn new() -> Self {
+		Self {
+			order: Vec::new(),
+			next: 0,
+		}
+	}
+}
+
+impl<C> Default for Scanline<C> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<St: State, Sp: Space<St>> SelectionHeuristic<St, Sp> for Scanline<Sp::Coordinate> {
+	fn select(&mut self, context: &mut SelectionContext<St, Sp>) -> Option<Sp::Coordinate> {
+		if self.order.is_empty() {
+			self.order = context.space().coordinate_list().into_vec();
+		}
+		while self.next < self.order.len() {
+			let coord = self.order[self.next];
+			if context.is_unresolved(coord) {
+				return Some(coord);
+			}
+			self.next += 1;
+		}
+		None
+	}
+	
+	fn reset(&mut self) {
+		self.order.clear();
+		self.next = 0;
+	}
+}
+
+/// Observes a uniformly random unresolved cell, regardless of entropy.
+/// 
+/// Cells are drawn from a list, and those which have been resolved since are
+/// only swapped out of it once they're drawn.
+#[derive(Clone, Debug)]
+pub struct RandomCell<C> {
+	cells: Vec<C>,
+	built: bool,
+}
+
+impl<C> RandomCell<C> {
+	pub fn new() -> Self {
+		Self {
+			cells: Vec::new(),
+			built: false,
+		}
+	}
+}
+
+impl<C> Default for RandomCell<C> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<St: State, Sp: Space<St>> SelectionHeuristic<St, Sp> for RandomCell<Sp::Coordinate> {
+	fn select(&mut self, context: &mut SelectionContext<St, Sp>) -> Option<Sp::Coordinate> {
+		if !self.built {
+			self.cells.clear();
+			self.cells.extend(context.unresolved());
+			self.built = true;
+		}
+		while !self.cells.is_empty() {
+			let index = context.rng.gen_range(0..self.cells.len());
+			let coord = self.cells[index];
+			if context.is_unresolved(coord) {
+				return Some(coord);
+			}
+			self.cells.swap_remove(index);
+		}
+		None
+	}
+	
+	fn reset(&mut self) {
+		self.cells.clear();
+		self.built = false;
+	}
+}
+
+/// Observes the unresolved cell closest to the last observed cell, counting
+/// distance in neighbor steps, so that the collapsed area grows outward from
+/// where it started. Between equally close cells, the one with the lowest
+/// entropy is picked. Falls back to the lowest entropy cell overall when
+/// there is nothing reachable from the last observed cell.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Growth;
+
+impl<St: State, Sp: Space<St>> SelectionHeuristic<St, Sp> for Growth {
+	fn select(&mut self, context: &mut SelectionContext<St, Sp>) -> Option<Sp::Coordinate> {
+		if let Some(last_observed) = context.last_observed() {
+			let mut visited = BTreeSet::new();
+			let mut frontier = vec![last_observed];
+			let mut next_frontier = Vec::new();
+			let mut neighbors = Vec::new();
+			visited.insert(last_observed);
+			while !frontier.is_empty() {
+				next_frontier.clear();
+				for coord in &frontier {
+					context.neighbors(*coord, &mut neighbors);
+					for neighbor in &neighbors {
+						if visited.insert(*neighbor) {
+							next_frontier.push(*neighbor);
+						}
+					}
+				}
+				let unresolved: Vec<_> = next_frontier.iter().copied().filter(|coord| context.is_unresolved(*coord)).collect();
+				if !unresolved.is_empty() {
+					return lowest_entropy(context, unresolved.iter());
+				}
+				std::mem::swap(&mut frontier, &mut next_frontier);
+			}
+		}
+		let unresolved = context.unresolved;
+		lowest_entropy(context, unresolved.iter())
+	}
+}
@@ -11,102 +11,222 @@ mod collapse_rule;
 mod state;
 mod set_state;
 mod all_state;
+mod contradiction;
+mod stats;
+mod collapser;
+mod selection_heuristic;
+mod seed;
+mod collapse_listener;
+mod parallel;
+mod propagator;
+mod global_constraint;
+mod cancellation;
 pub mod square_grid;
 pub mod bitset_state;
+pub mod wide_bitset_state;
+pub mod dyn_bitset_state;
 pub mod hashset_state;
 pub mod set_rule;
+pub mod heuristics;
+pub mod chunked_world;
+pub mod constraints;
 
-use std::{collections::{HashSet, VecDeque}};
+#[cfg(feature = "derive")]
+pub use kahuna_derive::Tiles;
 
-use rand::{thread_rng, Rng};
+use std::{panic, sync::atomic::{AtomicBool, Ordering}, thread};
+
+use rand::{rngs::StdRng, thread_rng, Rng, RngCore, SeedableRng};
 pub use space::*;
 pub use state::*;
 pub use collapse_rule::*;
 pub use set_state::*;
 pub use all_state::*;
+pub use contradiction::*;
+pub use stats::*;
+pub use collapser::*;
+pub use selection_heuristic::*;
+pub use seed::*;
+pub use collapse_listener::*;
+pub use parallel::*;
+pub use propagator::*;
+pub use global_constraint::*;
+pub use cancellation::*;
 
-fn find_next_to_collapse<St: State, Sp: Space<St>>(unresoved_set: &mut HashSet<Sp::Coordinate>, lowest_entropy_set: &mut Vec<Sp::Coordinate>, resolved_set: &mut HashSet<Sp::Coordinate>, space: &Sp) -> Option<Sp::Coordinate> {
-	let mut lowest_entropy = std::u32::MAX;
-	lowest_entropy_set.clear();
-	resolved_set.clear();
-	for unresolved in unresoved_set.iter() {
-		let entropy = space[*unresolved].entropy();
-		if entropy == 0 {
-			resolved_set.insert(*unresolved);
-		} else if entropy < lowest_entropy {
-			lowest_entropy = entropy;
-			lowest_entropy_set.clear();
-			lowest_entropy_set.push(*unresolved);
-		} else if entropy == lowest_entropy {
-			lowest_entropy_set.push(*unresolved);
-		}
-	}
-	unresoved_set.retain(|x| !resolved_set.contains(x));
-	if lowest_entropy_set.len() == 0 {
-		return None;
-	} else {
-		Some(lowest_entropy_set[thread_rng().gen_range(0..lowest_entropy_set.len())])
-	}
-}
+/// Result of running [collapse] on a space
+pub type CollapseResult<St, Sp> = Result<CollapseStats, Contradiction<<Sp as Space<St>>::Coordinate, St>>;
 
 /// Perform the wave function collapse algorithm on a given state-space with
 /// the provided collapse rule.
-pub fn collapse<Rule: CollapseRule<St, Sp>, St: State, Sp: Space<St>>(space: &mut Sp, rule: &Rule) {
-	let mut unresolved_set = HashSet::new();
-	let mut resolved_set = HashSet::new();
-	let mut lowest_entropy_set = Vec::new();
-	let neighbor_directions = rule.neighbor_offsets();
-	for coord in &space.coordinate_list()[..] {
-		if space[*coord].entropy() > 0 {
-			unresolved_set.insert(*coord);
-		}
-	}
-	let mut neighbors = vec![None; neighbor_directions.len()].into_boxed_slice();
-	let mut neighbor_states = vec![Option::<St>::None; neighbor_directions.len()].into_boxed_slice();
-	let mut to_propogate = VecDeque::new();
-	
-	for coordinate in unresolved_set.iter() {
-		to_propogate.push_back(*coordinate);
-	}
-	run_propogation(space, rule, &mut to_propogate, &neighbor_directions, &mut neighbors, &mut neighbor_states);
-	
-	while let Some(to_collapse) = find_next_to_collapse(&mut unresolved_set, &mut lowest_entropy_set, &mut resolved_set, space) {
-		to_propogate.clear();
-		space.neighbors(to_collapse, &neighbor_directions, &mut neighbors);
-		for i in 0 .. neighbor_directions.len() {
-			neighbor_states[i] = neighbors[i].map(|coord| space[coord].clone());
-		}
-		rule.observe(&mut space[to_collapse], &neighbor_states[..]);
-		for i in 0..neighbor_directions.len() {
-			if let Some(neighbor_coord) = neighbors[i] {
-				to_propogate.push_back(neighbor_coord);
+/// 
+/// Returns a [Contradiction] if some cell is left without any possible state,
+/// in which case the space is left partially collapsed.
+pub fn collapse<Rule: CollapseRule<St, Sp>, St: State, Sp: Space<St>>(space: &mut Sp, rule: &Rule) -> CollapseResult<St, Sp> {
+	collapse_with_rng(space, rule, &mut thread_rng())
+}
+
+/// Perform the wave function collapse algorithm using the given source of
+/// randomness.
+/// 
+/// Cells are visited in coordinate order, so as long as the rule only draws
+/// randomness from `rng`, the same seeded rng, rule and initial space will
+/// always produce the same result.
+pub fn collapse_with_rng<Rule: CollapseRule<St, Sp>, St: State, Sp: Space<St>, R: RngCore>(space: &mut Sp, rule: &Rule, rng: &mut R) -> CollapseResult<St, Sp> {
+	Collapser::new(space, rule).with_rng(rng).run()
+}
+
+/// Perform the wave function collapse algorithm with backtracking.
+/// 
+/// When a contradiction is found, the most recent observation and every
+/// change it caused are undone, and the observed state is banned from that
+/// cell with [CollapseRule::ban] before trying again. Gives up and returns the
+/// last [Contradiction] after `max_backtracks` observations have been undone.
+pub fn collapse_backtracking<Rule: CollapseRule<St, Sp>, St: State, Sp: Space<St>>(space: &mut Sp, rule: &Rule, max_backtracks: usize) -> CollapseResult<St, Sp> {
+	collapse_backtracking_with_rng(space, rule, max_backtracks, &mut thread_rng())
+}
+
+/// Perform the wave function collapse algorithm with backtracking, using the
+/// given source of randomness. See [collapse_backtracking] and
+/// [collapse_with_rng].
+pub fn collapse_backtracking_with_rng<Rule: CollapseRule<St, Sp>, St: State, Sp: Space<St>, R: RngCore>(space: &mut Sp, rule: &Rule, max_backtracks: usize, rng: &mut R) -> CollapseResult<St, Sp> {
+	Collapser::new(space, rule).with_rng(rng).with_backtracking(max_backtracks).run()
+}
+
+/// Perform the wave function collapse algorithm, starting over from the
+/// initial state of the space whenever a contradiction is found. Each attempt
+/// uses a fresh rng seeded from a random base seed. See
+/// [collapse_with_retries_seeded].
+pub fn collapse_with_retries<Rule: CollapseRule<St, Sp>, St: State, Sp: Space<St>>(space: &mut Sp, rule: &Rule, max_attempts: usize) -> CollapseResult<St, Sp> {
+	collapse_with_retries_seeded(space, rule, max_attempts, thread_rng().gen())
+}
+
+/// Perform the wave function collapse algorithm, starting over from the
+/// initial state of the space whenever a contradiction is found.
+/// 
+/// Attempt `n` (counting from zero) uses a [StdRng] seeded with
+/// `derive_seed(seed, n)`, so a given seed always takes the same number of
+/// attempts and gives the same result. The returned stats add up the work of
+/// every attempt, with [CollapseStats::attempts] counting how many were needed.
+/// 
+/// At least one attempt is always made. If every attempt fails, the last
+/// [Contradiction] is returned and the space is left as that attempt left it.
+pub fn collapse_with_retries_seeded<Rule: CollapseRule<St, Sp>, St: State, Sp: Space<St>>(space: &mut Sp, rule: &Rule, max_attempts: usize, seed: u64) -> CollapseResult<St, Sp> {
+	let initial_states: Vec<_> = space.coordinate_list().iter().map(|coord| (*coord, space[*coord].clone())).collect();
+	let mut stats = CollapseStats::default();
+	let mut attempt = 0;
+	loop {
+		if attempt > 0 {
+			for (coord, state) in &initial_states {
+				space[*coord] = state.clone();
 			}
 		}
-		run_propogation(space, rule, &mut to_propogate, &neighbor_directions, &mut neighbors, &mut neighbor_states);
+		let rng = StdRng::seed_from_u64(derive_seed(seed, attempt as u64));
+		let mut collapser = Collapser::new(space, rule).with_rng(rng);
+		let result = collapser.run();
+		stats += collapser.stats();
+		attempt += 1;
+		match result {
+			Ok(_) => return Ok(stats),
+			Err(contradiction) if attempt >= max_attempts => return Err(contradiction),
+			Err(_) => {},
+		}
 	}
 }
 
-fn run_propogation<Rule: CollapseRule<St, Sp>, St: State, Sp: Space<St>>(space: &mut Sp, rule: &Rule, to_propogate: &mut VecDeque<Sp::Coordinate>, neighbor_directions: &[Sp::CoordinateDelta], neighbors: &mut [Option<Sp::Coordinate>], neighbor_states: &mut [Option<St>]) {
-	while let Some(propogating) = to_propogate.pop_front() {
-		let entropy_before = space[propogating].entropy();
-		
-		if entropy_before != 0 {
-			space.neighbors(propogating, &neighbor_directions, neighbors);
-			for i in 0 .. neighbor_directions.len() {
-				neighbor_states[i] = neighbors[i].map(|coord| space[coord].clone());
-			}
-			rule.collapse(&mut space[propogating], &neighbor_states[..]);
-			let entropy_after = space[propogating].entropy();
-			
-			if entropy_after < entropy_before {
-				for i in 0 .. neighbor_directions.len() {
-					if let Some(neighbor) = neighbors[i] {
-						if space[neighbor].entropy() != 0 {
-							to_propogate.push_back(neighbor);
-						}
-					}
+/// Perform the wave function collapse algorithm with `attempts` attempts
+/// racing each other on separate threads, keeping the first one to succeed.
+/// See [collapse_racing_seeded].
+pub fn collapse_racing<Rule, St, Sp>(space: &mut Sp, rule: &Rule, attempts: usize) -> CollapseResult<St, Sp>
+	where Rule: CollapseRule<St, Sp> + Sync, St: State + Send, Sp: Space<St> + Clone + Send, Sp::Coordinate: Send {
+	collapse_racing_seeded(space, rule, attempts, thread_rng().gen())
+}
+
+/// Perform the wave function collapse algorithm with `attempts` attempts
+/// racing each other on separate threads, keeping the first one to succeed.
+/// 
+/// Each attempt collapses its own clone of the space, with attempt `n` using a
+/// [StdRng] seeded with `derive_seed(seed, n)` as in
+/// [collapse_with_retries_seeded]. Once one attempt succeeds, the others are
+/// stopped through a shared [CancellationToken], even partway through
+/// propagating, and its space is copied into `space`. Which attempt finishes first
+/// can vary between runs, so unlike the other collapse functions the result
+/// isn't reproducible from the seed alone.
+/// 
+/// The returned stats are those of the winning attempt. If every attempt
+/// fails, `space` is left as it was and the [Contradiction] from the first
+/// attempt is returned.
+pub fn collapse_racing_seeded<Rule, St, Sp>(space: &mut Sp, rule: &Rule, attempts: usize, seed: u64) -> CollapseResult<St, Sp>
+	where Rule: CollapseRule<St, Sp> + Sync, St: State + Send, Sp: Space<St> + Clone + Send, Sp::Coordinate: Send {
+	let cancellation = CancellationToken::new();
+	let finished = AtomicBool::new(false);
+	let results: Vec<_> = thread::scope(|scope| {
+		let threads: Vec<_> = (0..attempts.max(1)).map(|attempt| {
+			let mut attempt_space = space.clone();
+			let cancellation = cancellation.clone();
+			let finished = &finished;
+			scope.spawn(move || {
+				let rng = StdRng::seed_from_u64(derive_seed(seed, attempt as u64));
+				let mut collapser = Collapser::new(&mut attempt_space, rule)
+					.with_rng(rng)
+					.with_cancellation(cancellation.clone());
+				if let Err(contradiction) = collapser.run() {
+					return Some(Err(contradiction));
 				}
-			}
+				if !collapser.is_done() {
+					// cancelled by the winner
+					return None;
+				}
+				let stats = collapser.stats();
+				drop(collapser);
+				// another attempt may have finished in the meantime
+				match finished.compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed) {
+					Ok(_) => {
+						cancellation.cancel();
+						Some(Ok((attempt_space, stats)))
+					},
+					Err(_) => None,
+				}
+			})
+		}).collect();
+		threads.into_iter()
+			.map(|thread| thread.join().unwrap_or_else(|payload| panic::resume_unwind(payload)))
+			.collect()
+	});
+	let mut first_contradiction = None;
+	for result in results.into_iter().flatten() {
+		match result {
+			Ok((winning_space, stats)) => {
+				*space = winning_space;
+				return Ok(stats);
+			},
+			Err(contradiction) => {
+				first_contradiction.get_or_insert(contradiction);
+			},
 		}
 	}
+	Err(first_contradiction.expect("every attempt failed without a contradiction"))
+}
+
+/// Reset every cell in `region` to the state given by `init_fn`, ready to be
+/// collapsed again with [Collapser::with_region].
+pub fn reset_region<St: State, Sp: Space<St>>(space: &mut Sp, region: &[Sp::Coordinate], init_fn: impl Fn(Sp::Coordinate) -> St) {
+	for coord in region {
+		space[*coord] = init_fn(*coord);
+	}
+}
+
+/// Re-generate part of an already collapsed space.
+/// 
+/// Every cell in `region` is reset to [AllState::all], narrowed down by the
+/// cells around it, and collapsed again, so that the new contents fit with
+/// the rest of the space. Cells outside the region are left as they are.
+pub fn regenerate<Rule: CollapseRule<St, Sp>, St: State + AllState, Sp: Space<St>>(space: &mut Sp, rule: &Rule, region: &[Sp::Coordinate]) -> CollapseResult<St, Sp> {
+	regenerate_with_rng(space, rule, region, &mut thread_rng())
+}
+
+/// Re-generate part of an already collapsed space using the given source of
+/// randomness. See [regenerate] and [collapse_with_rng].
+pub fn regenerate_with_rng<Rule: CollapseRule<St, Sp>, St: State + AllState, Sp: Space<St>, R: RngCore>(space: &mut Sp, rule: &Rule, region: &[Sp::Coordinate], rng: &mut R) -> CollapseResult<St, Sp> {
+	reset_region(space, region, |_| St::all());
+	Collapser::new(space, rule).with_rng(rng).with_region(region).run()
 }
@@ -0,0 +1,15 @@
+/// Bounds needed to share a type between threads when the `parallel` feature
+/// is enabled. Without the feature, every type satisfies this.
+#[cfg(feature = "parallel")]
+pub trait ParallelBounds: Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync + ?Sized> ParallelBounds for T {}
+
+/// Bounds needed to share a type between threads when the `parallel` feature
+/// is enabled. Without the feature, every type satisfies this.
+#[cfg(not(feature = "parallel"))]
+pub trait ParallelBounds {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T: ?Sized> ParallelBounds for T {}
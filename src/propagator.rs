@@ -0,0 +1,31 @@
+use crate::{State, Space};
+
+/// Propagates the effects of cells losing possible states through a space.
+/// 
+/// By default, collapse propagates a change by collapsing each neighbor of the
+/// changed cell with [crate::CollapseRule::collapse], and repeating for every
+/// neighbor which changes as a result. A Propagator replaces this, for rules
+/// which can propagate faster by keeping track of extra information between
+/// changes, such as [crate::set_rule::SupportPropagator].
+pub trait Propagator<St: State, Sp: Space<St>> {
+	/// Remove states which are no longer allowed from cells of `space`, after
+	/// the cells in `changed` lost states. `changed` holds each of those cells
+	/// along with its state from before the change.
+	/// 
+	/// The first call after [Self::reset] should check every cell in the
+	/// space, since cells may have changed in any way before it.
+	/// 
+	/// Every cell this modifies must be added to `modified` once, along with
+	/// its state from before it was first modified. If a cell runs out of
+	/// possible states, its coordinate is returned as an error.
+	/// 
+	/// `interrupted` should be checked every so often. Once it returns true,
+	/// return `Ok(false)` straight away, keeping whatever is left to do so that
+	/// the next call, which may have no changed cells, can carry on with it.
+	/// Otherwise returns `Ok(true)` once every change has been propagated.
+	fn propagate(&mut self, space: &mut Sp, changed: &[(Sp::Coordinate, St)], modified: &mut Vec<(Sp::Coordinate, St)>, interrupted: &dyn Fn() -> bool) -> Result<bool, Sp::Coordinate>;
+	/// Called when collapse starts, and whenever cells may have regained
+	/// states (such as after backtracking), so that anything kept between
+	/// calls to [Self::propagate] can be discarded.
+	fn reset(&mut self) {}
+}
@@ -0,0 +1,10 @@
+/// Derives an independent seed from `seed` for the numbered `stream`, such
+/// as the n-th attempt of a collapse. The same inputs always give the same
+/// seed on every platform.
+pub fn derive_seed(seed: u64, stream: u64) -> u64 {
+	// SplitMix64 finalizer over the combined inputs
+	let mut x = seed ^ stream.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+	x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	x ^ (x >> 31)
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeSet;
+
+use rand::RngCore;
+
+use crate::{State, Space, CollapseRule};
+
+/// Chooses which cell to observe next during collapse.
+/// 
+/// Implementations for common strategies are in [crate::heuristics].
+pub trait SelectionHeuristic<St: State, Sp: Space<St>> {
+	/// Pick the next cell to observe from the unresolved cells in `context`,
+	/// or `None` to stop. Only cells with non-zero entropy should be picked.
+	fn select(&mut self, context: &mut SelectionContext<St, Sp>) -> Option<Sp::Coordinate>;
+	/// Called whenever the cell at `coord` loses possible states, including
+	/// when it becomes resolved. Heuristics which keep track of cells
+	/// between calls to `select()` can use this to stay up to date without
+	/// scanning the whole space.
+	fn update(&mut self, coord: Sp::Coordinate, context: &mut SelectionContext<St, Sp>) {
+		let _ = (coord, context);
+	}
+	/// Called when collapse starts, and whenever cells may have become
+	/// unresolved again (such as after backtracking), so that any cached
+	/// state can be discarded.
+	fn reset(&mut self) {}
+}
+
+/// Everything a [SelectionHeuristic] can look at to make its choice
+pub struct SelectionContext<'c, St: State, Sp: Space<St>> {
+	pub(crate) space: &'c Sp,
+	pub(crate) rule: &'c dyn CollapseRule<St, Sp>,
+	pub(crate) unresolved: &'c BTreeSet<Sp::Coordinate>,
+	pub(crate) neighbor_directions: &'c [Sp::CoordinateDelta],
+	pub(crate) last_observed: Option<Sp::Coordinate>,
+	pub(crate) rng: &'c mut dyn RngCore,
+}
+
+impl<'c, St: State, Sp: Space<St>> SelectionContext<'c, St, Sp> {
+	/// The space being collapsed
+	pub fn space(&self) -> &Sp {
+		self.space
+	}
+	
+	/// Every cell which has not been resolved yet, in coordinate order
+	pub fn unresolved(&self) -> impl Iterator<Item = Sp::Coordinate> + '_ {
+		self.unresolved.iter().copied()
+	}
+	
+	/// Number of cells which have not been resolved yet
+	pub fn unresolved_count(&self) -> usize {
+		self.unresolved.len()
+	}
+	
+	/// Checks if the cell at `coord` has not been resolved yet
+	pub fn is_unresolved(&self, coord: Sp::Coordinate) -> bool {
+		self.unresolved.contains(&coord)
+	}
+	
+	/// Entropy of the cell at `coord`, using the rule's
+	/// [CollapseRule::weighted_entropy] when it provides one and
+	/// [State::entropy] otherwise
+	pub fn entropy(&self, coord: Sp::Coordinate) -> f64 {
+		let cell = &self.space[coord];
+		match self.rule.weighted_entropy(cell) {
+			Some(weighted_entropy) => weighted_entropy as f64,
+			None => cell.entropy() as f64,
+		}
+	}
+	
+	/// The most recently observed cell, if any
+	pub fn last_observed(&self) -> Option<Sp::Coordinate> {
+		self.last_observed
+	}
+	
+	/// Collects the coordinates of the neighbors of `coord` under the rule
+	/// into `neighbors`, clearing it first.
+	pub fn neighbors(&self, coord: Sp::Coordinate, neighbors: &mut Vec<Sp::Coordinate>) {
+		let mut neighbor_coords = vec![None; self.neighbor_directions.len()];
+		self.space.neighbors(coord, self.neighbor_directions, &mut neighbor_coords);
+		neighbors.clear();
+		neighbors.extend(neighbor_coords.into_iter().flatten());
+	}
+	
+	/// Source of randomness for the choice
+	pub fn rng(&mut self) -> &mut dyn RngCore {
+		self.rng
+	}
+}
@@ -1,24 +1,145 @@
-use rand::{thread_rng, Rng};
-use crate::{SetState, State, Space, AllState, CollapseRule, InvertDelta};
+use std::{collections::HashMap, hash::Hash};
 
-pub trait SetCollapseObserver<S: State> {
-	fn observe(&self, cell: &mut S, neighbors: &[Option<S>]);
+use rand::{Rng, RngCore};
+use crate::{SetState, State, Space, AllState, CollapseRule, InvertDelta, ParallelBounds, Propagator};
+use crate::bitset_state::BitsetState;
+
+pub trait SetCollapseObserver<S: State>: ParallelBounds {
+	fn observe(&self, cell: &mut S, neighbors: &[Option<S>], rng: &mut dyn RngCore);
+	/// Weight-aware entropy of `cell`, see [CollapseRule::weighted_entropy]
+	fn weighted_entropy(&self, cell: &S) -> Option<f32> {
+		let _ = cell;
+		None
+	}
 }
 
 #[derive(Clone)]
 pub struct UniformSetCollapseObserver;
 
 impl<S: SetState + State + Clone> SetCollapseObserver<S> for UniformSetCollapseObserver {
-    fn observe(&self, cell: &mut S, _: &[Option<S>]) {
+    fn observe(&self, cell: &mut S, _: &[Option<S>], rng: &mut dyn RngCore) {
         let mut final_states = Vec::new();
 		cell.collect_final_states(&mut final_states);
-		*cell = final_states[thread_rng().gen_range(0..final_states.len())].clone();
+		if final_states.is_empty() {
+			return;
+		}
+		*cell = final_states[rng.gen_range(0..final_states.len())].clone();
     }
 }
 
+type AllowedNeighbors<S> = Box<[Option<S>]>;
+
+/// Observer which picks final states with probability proportional to their
+/// weights, and reports the Shannon entropy of those weights so that the most
+/// constrained cells are observed first.
+/// 
+/// Final states without a weight have a weight of 1. The entropy is worked out
+/// with basic arithmetic only, so that it comes out the same on every platform
+/// and seeded collapse picks cells in the same order everywhere.
+#[derive(Clone)]
+pub struct WeightedSetCollapseObserver<S> {
+	weights: HashMap<S, f32>,
+}
+
+impl<S: SetState + State + Eq + Hash> WeightedSetCollapseObserver<S> {
+	/// Create a new WeightedSetCollapseObserver
+	/// 
+	/// * `weights` - Pairs of states and weights. Every final state in a pair
+	///   is given that weight.
+	pub fn new(weights: &[(S, f32)]) -> Self {
+		let mut final_weights = HashMap::new();
+		for (state, weight) in weights {
+			assert!(*weight >= 0.0);
+			let mut final_states = Vec::new();
+			state.collect_final_states(&mut final_states);
+			for final_state in final_states {
+				// the first weight given for a state wins
+				final_weights.entry(final_state).or_insert(*weight);
+			}
+		}
+		Self {
+			weights: final_weights
+		}
+	}
+	
+	fn weight(&self, state: &S) -> f32 {
+		self.weights.get(state).copied().unwrap_or(1.0)
+	}
+}
+
+impl<S: SetState + State + Eq + Hash> SetCollapseObserver<S> for WeightedSetCollapseObserver<S> {
+	fn observe(&self, cell: &mut S, _: &[Option<S>], rng: &mut dyn RngCore) {
+		let mut final_states = Vec::new();
+		cell.collect_final_states(&mut final_states);
+		if final_states.is_empty() {
+			return;
+		}
+		let total_weight: f32 = final_states.iter().map(|state| self.weight(state)).sum();
+		if total_weight <= 0.0 {
+			*cell = final_states[rng.gen_range(0..final_states.len())].clone();
+			return;
+		}
+		let mut choice = rng.gen_range(0.0..total_weight);
+		for final_state in &final_states {
+			let weight = self.weight(final_state);
+			if choice < weight {
+				*cell = final_state.clone();
+				return;
+			}
+			choice -= weight;
+		}
+		// rounding can leave a sliver of weight past the last state
+		*cell = final_states[final_states.len() - 1].clone();
+	}
+	
+	fn weighted_entropy(&self, cell: &S) -> Option<f32> {
+		let mut final_states = Vec::new();
+		cell.collect_final_states(&mut final_states);
+		let mut total_weight = 0.0;
+		let mut total_weight_log_weight = 0.0;
+		for final_state in &final_states {
+			let weight = self.weight(final_state) as f64;
+			if weight > 0.0 {
+				total_weight += weight;
+				total_weight_log_weight += weight * ln(weight);
+			}
+		}
+		if total_weight <= 0.0 {
+			return Some(0.0);
+		}
+		Some((ln(total_weight) - total_weight_log_weight / total_weight) as f32)
+	}
+}
+
+/// Natural logarithm of a positive, finite `x`. Unlike [f64::ln], which is
+/// left to the platform's math library, this only uses basic arithmetic, so
+/// the result is the same everywhere.
+fn ln(x: f64) -> f64 {
+	let bits = x.to_bits();
+	// split x into 2^exponent * mantissa, with the mantissa between sqrt(1/2)
+	// and sqrt(2)
+	let mut exponent = ((bits >> 52) & 0x7ff) as i64 - 1023;
+	let mut mantissa = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+	if mantissa > std::f64::consts::SQRT_2 {
+		exponent += 1;
+		mantissa /= 2.0;
+	}
+	// ln(mantissa) = 2 atanh(s), where s is small enough for the series to
+	// converge to full precision within a dozen terms
+	let s = (mantissa - 1.0) / (mantissa + 1.0);
+	let s_squared = s * s;
+	let mut power = s;
+	let mut series = 0.0;
+	for k in 0..12 {
+		series += power / (2 * k + 1) as f64;
+		power *= s_squared;
+	}
+	exponent as f64 * std::f64::consts::LN_2 + 2.0 * series
+}
+
 pub struct SetCollapseRule<S: SetState + State + Sized, Sp: Space<S>, O: SetCollapseObserver<S>> {
 	neighbor_offsets: Box<[Sp::CoordinateDelta]>,
-	state_rules: Box<[(S, Box<[Option<S>]>)]>,
+	state_rules: Box<[(S, AllowedNeighbors<S>)]>,
 	observer: O,
 }
 
@@ -33,7 +154,7 @@ impl<S: SetState + Clone> StateRule<S> {
 			self.allowed_neighbors.push(None);
 		}
 		if let Some(allowed_neighbors) = &mut self.allowed_neighbors[neighbor_index] {
-			allowed_neighbors.set_states(&allowed);
+			allowed_neighbors.set_states(allowed);
 		} else {
 			self.allowed_neighbors[neighbor_index] = Some(allowed.clone());
 		}
@@ -49,7 +170,7 @@ pub struct SetCollapseRuleBuilder<S: SetState + State, Sp: Space<S>, O: SetColla
 	observer: O
 }
 
-impl<S: AllState + SetState + State + PartialEq, Sp: Space<S>, O: SetCollapseObserver<S> + Clone> SetCollapseRuleBuilder<S, Sp, O>
+impl<S: SetState + State, Sp: Space<S>, O: SetCollapseObserver<S> + Clone> SetCollapseRuleBuilder<S, Sp, O>
 	where Sp::CoordinateDelta: Eq + Clone + InvertDelta {
 	pub fn new(observer: O) -> Self {
 		Self {
@@ -114,9 +235,13 @@ impl<S: AllState + SetState + State + PartialEq, Sp: Space<S>, O: SetCollapseObs
 		&mut self.state_rules[index]
 	}
 	
-	pub fn build(self) -> SetCollapseRule<S, Sp, O> {
+	/// Build the rule, where `all` is the state with every final state
+	/// possible. Final states without any allowed neighbors can only be placed
+	/// where all their neighbors are outside of world-space. See [Self::build]
+	/// for states implementing [AllState].
+	pub fn build_from(self, all: &S) -> SetCollapseRule<S, Sp, O> {
 		let mut state_rules = Vec::new();
-		let mut remaining_state = S::all();
+		let mut remaining_state = all.clone();
 		for mut proto_rule in self.state_rules {
 			while proto_rule.allowed_neighbors.len() < self.neighbor_offsets.len() {
 				proto_rule.allowed_neighbors.push(None);
@@ -137,6 +262,13 @@ impl<S: AllState + SetState + State + PartialEq, Sp: Space<S>, O: SetCollapseObs
 	}
 }
 
+impl<S: AllState + SetState + State, Sp: Space<S>, O: SetCollapseObserver<S> + Clone> SetCollapseRuleBuilder<S, Sp, O>
+	where Sp::CoordinateDelta: Eq + Clone + InvertDelta {
+	pub fn build(self) -> SetCollapseRule<S, Sp, O> {
+		self.build_from(&S::all())
+	}
+}
+
 /// A collapse rule implementation that works with implementors of [crate::SetState]
 impl<S: SetState + State, Sp: Space<S>, O: SetCollapseObserver<S>> CollapseRule<S, Sp> for SetCollapseRule<S, Sp, O>
 	where Sp::CoordinateDelta: Clone {
@@ -163,8 +295,234 @@ impl<S: SetState + State, Sp: Space<S>, O: SetCollapseObserver<S>> CollapseRule<
 		}
     }
 
-    fn observe(&self, cell: &mut S, neighbors: &[Option<S>]) {
-        self.observer.observe(cell, neighbors);
+    fn observe(&self, cell: &mut S, neighbors: &[Option<S>], rng: &mut dyn RngCore) {
+        self.observer.observe(cell, neighbors, rng);
     }
+	
+	fn ban(&self, cell: &mut S, observed: &S) -> bool {
+		cell.clear_states(observed);
+		true
+	}
+	
+	fn weighted_entropy(&self, cell: &S) -> Option<f32> {
+		self.observer.weighted_entropy(cell)
+	}
 }
 
+
+/// How many removed states [SupportPropagator] propagates between checks for
+/// interruption
+const INTERRUPT_CHECK_INTERVAL: usize = 64;
+
+/// Propagator for [SetCollapseRule]s over [BitsetState]s which counts how
+/// many of the states of each cell's neighbors allow each of its own states,
+/// as in the AC-4 algorithm. When a cell loses a state, only the counts of the
+/// neighbor states it allowed are updated, instead of checking every state of
+/// every neighbor against the rule again.
+/// 
+/// Removes the same states as propagating with the rule itself, but tends to
+/// be faster for larger tilesets. The counts are rebuilt for the whole space
+/// whenever collapse backtracks. Use it with [crate::Collapser::with_propagator].
+pub struct SupportPropagator<const FINAL_STATE_COUNT: u32, Sp: Space<BitsetState<FINAL_STATE_COUNT>>> {
+	directions: Box<[Sp::CoordinateDelta]>,
+	direction_count: usize,
+	/// Index of the opposite of each direction
+	opposites: Box<[usize]>,
+	/// For each direction and state, the states a neighbor in that direction
+	/// can have to allow it
+	allowed_neighbors: Box<[u64]>,
+	/// For each direction and state, the states of a cell which are allowed by
+	/// a neighbor in that direction having that state
+	allowed_by: Box<[u64]>,
+	built: bool,
+	coordinates: Box<[Sp::Coordinate]>,
+	indices: HashMap<Sp::Coordinate, usize>,
+	/// For each cell and direction, the index of the neighbor cell
+	neighbors: Vec<Option<usize>>,
+	/// For each cell, direction and state, the number of states of the neighbor
+	/// in that direction which allow that state
+	support_counts: Vec<u8>,
+	removals: Vec<(usize, u32)>,
+	/// Generation in which each cell was last added to `modified`
+	modified_generation: Vec<u32>,
+	generation: u32,
+}
+
+impl<const FINAL_STATE_COUNT: u32, Sp: Space<BitsetState<FINAL_STATE_COUNT>>> SupportPropagator<FINAL_STATE_COUNT, Sp>
+	where Sp::CoordinateDelta: Clone + PartialEq + InvertDelta {
+	/// Create a new SupportPropagator for `rule`
+	pub fn new<O: SetCollapseObserver<BitsetState<FINAL_STATE_COUNT>>>(rule: &SetCollapseRule<BitsetState<FINAL_STATE_COUNT>, Sp, O>) -> Self {
+		let direction_count = rule.neighbor_offsets.len();
+		// the rule builder always adds the opposite of each direction
+		let opposites = rule.neighbor_offsets.iter()
+			.map(|direction| {
+				let opposite = direction.invert_delta();
+				rule.neighbor_offsets.iter().position(|other| *other == opposite).unwrap()
+			})
+			.collect();
+		let mut allowed_neighbors = vec![0u64; direction_count * 64];
+		let mut allowed_by = vec![0u64; direction_count * 64];
+		for (state, allowed) in &rule.state_rules[..] {
+			let state_index = state.0.trailing_zeros() as usize;
+			for (direction, allowed) in allowed.iter().enumerate() {
+				if let Some(BitsetState(allowed)) = allowed {
+					allowed_neighbors[direction * 64 + state_index] = *allowed;
+					for neighbor_state in 0..64 {
+						if allowed & (1u64 << neighbor_state) != 0 {
+							allowed_by[direction * 64 + neighbor_state] |= 1u64 << state_index;
+						}
+					}
+				}
+			}
+		}
+		Self {
+			directions: rule.neighbor_offsets.clone(),
+			direction_count,
+			opposites,
+			allowed_neighbors: allowed_neighbors.into_boxed_slice(),
+			allowed_by: allowed_by.into_boxed_slice(),
+			built: false,
+			coordinates: Box::new([]),
+			indices: HashMap::new(),
+			neighbors: Vec::new(),
+			support_counts: Vec::new(),
+			removals: Vec::new(),
+			modified_generation: Vec::new(),
+			generation: 0,
+		}
+	}
+}
+
+impl<const FINAL_STATE_COUNT: u32, Sp: Space<BitsetState<FINAL_STATE_COUNT>>> SupportPropagator<FINAL_STATE_COUNT, Sp> {
+	fn support_index(&self, cell: usize, direction: usize, state: u32) -> usize {
+		(cell * self.direction_count + direction) * FINAL_STATE_COUNT as usize + state as usize
+	}
+	
+	/// Counts the support of every state in the space from scratch, removing
+	/// states which have none
+	fn build(&mut self, space: &mut Sp, modified: &mut Vec<(Sp::Coordinate, BitsetState<FINAL_STATE_COUNT>)>) -> Result<(), Sp::Coordinate> {
+		self.coordinates = space.coordinate_list();
+		let cell_count = self.coordinates.len();
+		self.indices = self.coordinates.iter().enumerate().map(|(i, coord)| (*coord, i)).collect();
+		
+		let mut neighbor_coords = vec![None; self.direction_count];
+		self.neighbors.clear();
+		for coord in &self.coordinates[..] {
+			space.neighbors(*coord, &self.directions, &mut neighbor_coords);
+			self.neighbors.extend(neighbor_coords.iter().map(|neighbor| neighbor.map(|neighbor| self.indices[&neighbor])));
+		}
+		
+		self.support_counts.clear();
+		self.support_counts.resize(cell_count * self.direction_count * FINAL_STATE_COUNT as usize, 0);
+		self.modified_generation.clear();
+		self.modified_generation.resize(cell_count, self.generation.wrapping_sub(1));
+		let mut unsupported = Vec::new();
+		for cell in 0..cell_count {
+			let states = space[self.coordinates[cell]].0;
+			for direction in 0..self.direction_count {
+				let Some(neighbor) = self.neighbors[cell * self.direction_count + direction] else {
+					continue;
+				};
+				let neighbor_states = space[self.coordinates[neighbor]].0;
+				for state in BitIter(states) {
+					let count = (neighbor_states & self.allowed_neighbors[direction * 64 + state as usize]).count_ones();
+					let index = self.support_index(cell, direction, state);
+					self.support_counts[index] = count as u8;
+					if count == 0 {
+						unsupported.push((cell, state));
+					}
+				}
+			}
+		}
+		// counts are only updated for states a cell still has, so removing the
+		// unsupported states afterwards keeps them exact
+		for (cell, state) in unsupported {
+			if space[self.coordinates[cell]].0 & (1u64 << state) != 0 {
+				self.remove(space, cell, state, modified)?;
+			}
+		}
+		Ok(())
+	}
+	
+	fn remove(&mut self, space: &mut Sp, cell: usize, state: u32, modified: &mut Vec<(Sp::Coordinate, BitsetState<FINAL_STATE_COUNT>)>) -> Result<(), Sp::Coordinate> {
+		let coord = self.coordinates[cell];
+		if self.modified_generation[cell] != self.generation {
+			self.modified_generation[cell] = self.generation;
+			modified.push((coord, space[coord]));
+		}
+		space[coord].0 &= !(1u64 << state);
+		self.removals.push((cell, state));
+		if space[coord].is_empty() {
+			return Err(coord);
+		}
+		Ok(())
+	}
+}
+
+impl<const FINAL_STATE_COUNT: u32, Sp: Space<BitsetState<FINAL_STATE_COUNT>>> Propagator<BitsetState<FINAL_STATE_COUNT>, Sp> for SupportPropagator<FINAL_STATE_COUNT, Sp> {
+	fn propagate(&mut self, space: &mut Sp, changed: &[(Sp::Coordinate, BitsetState<FINAL_STATE_COUNT>)], modified: &mut Vec<(Sp::Coordinate, BitsetState<FINAL_STATE_COUNT>)>, interrupted: &dyn Fn() -> bool) -> Result<bool, Sp::Coordinate> {
+		self.generation = self.generation.wrapping_add(1);
+		if !self.built {
+			self.built = true;
+			self.build(space, modified)?;
+		} else {
+			for (coord, before) in changed {
+				let cell = self.indices[coord];
+				for state in BitIter(before.0 & !space[*coord].0) {
+					self.removals.push((cell, state));
+				}
+			}
+		}
+		// counted from the start of each call, so that every call gets
+		// somewhere however little time it's given
+		let mut propagated = 0;
+		while let Some((cell, state)) = self.removals.pop() {
+			if propagated == INTERRUPT_CHECK_INTERVAL {
+				if interrupted() {
+					// the remaining removals are propagated on the next call
+					self.removals.push((cell, state));
+					return Ok(false);
+				}
+				propagated = 0;
+			}
+			propagated += 1;
+			for direction in 0..self.direction_count {
+				let Some(neighbor) = self.neighbors[cell * self.direction_count + direction] else {
+					continue;
+				};
+				// the neighbor sees this cell in the opposite direction
+				let opposite = self.opposites[direction];
+				let affected = space[self.coordinates[neighbor]].0 & self.allowed_by[opposite * 64 + state as usize];
+				for neighbor_state in BitIter(affected) {
+					let index = self.support_index(neighbor, opposite, neighbor_state);
+					self.support_counts[index] -= 1;
+					if self.support_counts[index] == 0 {
+						self.remove(space, neighbor, neighbor_state, modified)?;
+					}
+				}
+			}
+		}
+		Ok(true)
+	}
+	
+	fn reset(&mut self) {
+		self.built = false;
+		self.removals.clear();
+	}
+}
+
+/// Iterates over the indices of the set bits of a u64
+struct BitIter(u64);
+
+impl Iterator for BitIter {
+	type Item = u32;
+	
+	fn next(&mut self) -> Option<u32> {
+		if self.0 == 0 {
+			return None;
+		}
+		let bit = self.0.trailing_zeros();
+		self.0 &= self.0 - 1;
+		Some(bit)
+	}
+}
@@ -1,5 +1,7 @@
 use std::{ops::IndexMut, hash::Hash};
 
+use crate::ParallelBounds;
+
 /// Represents coordinate deltas which have an inverse - the delta which undoes
 /// the change represented by this delta.
 /// 
@@ -17,15 +19,15 @@ pub trait InvertDelta {
 /// In order to support arbitrary dimension and shape, two associated types are
 /// defined:
 /// - `Coordinate` is the index type for this space. Cells in the space are
-/// uniquely identified by coordinates.
+///   uniquely identified by coordinates.
 /// - `CoordinateDelta` represents adjacency relations between cells. In
-/// general, a collapse rule supplies a list of coordinate deltas to get
-/// neighbor cell coordinates.
-pub trait Space<T>: IndexMut<Self::Coordinate, Output = T> + 'static {
+///   general, a collapse rule supplies a list of coordinate deltas to get
+///   neighbor cell coordinates.
+pub trait Space<T>: IndexMut<Self::Coordinate, Output = T> + ParallelBounds + 'static {
 	/// Coordinates for cells in the space
-	type Coordinate: Copy + Hash + Ord;
+	type Coordinate: Copy + Hash + Ord + ParallelBounds + 'static;
 	/// Spatial relationship between cells for accessing neighbors
-	type CoordinateDelta: 'static;
+	type CoordinateDelta: ParallelBounds + 'static;
 	
 	/// Get every valid coordinate in the space.
 	fn coordinate_list(&self) -> Box<[Self::Coordinate]>;
@@ -34,9 +36,16 @@ pub trait Space<T>: IndexMut<Self::Coordinate, Output = T> + 'static {
 	/// * `coord` - Coordinate of the cell to find neighbors for
 	/// * `neighbor_directions` - List of neighbor cell offsets
 	/// * `neighbors` - Output list of neighbor coordinates. Must be at least
-	/// as long as neighbor_directions. Set to `None` for neighbors which are
-	/// out of bounds for the space.
+	///   as long as neighbor_directions. Set to `None` for neighbors which are
+	///   out of bounds for the space.
 	fn neighbors(&self, coord: Self::Coordinate, neighbor_directions: &[Self::CoordinateDelta], neighbors: &mut [Option<Self::Coordinate>]);
+	/// Get a dense index for `coord`, counting up from zero, if the space has
+	/// one. This lets collapse keep track of cells with flat arrays rather
+	/// than sets. Returns `None` by default.
+	fn index_of(&self, coord: Self::Coordinate) -> Option<usize> {
+		let _ = coord;
+		None
+	}
 }
 
 
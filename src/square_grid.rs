@@ -1,10 +1,11 @@
 use std::ops::{IndexMut, Index};
 
-use crate::{Space, InvertDelta};
+use crate::{Space, InvertDelta, ParallelBounds};
 
 /// Basic square grid implementing [crate::Space]
 /// 
 /// coordinates and coordinate directions are specified as `(isize, isize)`.
+#[derive(Clone)]
 pub struct SquareGrid<T> {
 	cells: Box<[T]>,
 	width: isize,
@@ -24,7 +25,7 @@ impl<T> SquareGrid<T> {
 	/// * `width` - width of the grid
 	/// * `height` - height of the grid
 	/// * `init_fn` - callback to set the initial state of each cell based on
-	/// coordinate
+	///   coordinate
 	pub fn new(width: isize, height: isize, init_fn: impl Fn(isize, isize) -> T) -> Self {
 		let mut cells = Vec::new();
 		for y in 0..height {
@@ -38,25 +39,41 @@ impl<T> SquareGrid<T> {
 			height,
 		}
 	}
+	
+	/// Get the coordinates of every cell in a rectangle, such as a region to
+	/// pass to [crate::regenerate]. Parts of the rectangle outside the grid
+	/// are left out.
+	/// 
+	/// * `x`, `y` - top left corner of the rectangle
+	/// * `width`, `height` - size of the rectangle
+	pub fn rect(&self, x: isize, y: isize, width: isize, height: isize) -> Box<[(isize, isize)]> {
+		let mut coords = Vec::new();
+		for cy in y.max(0)..(y + height).min(self.height) {
+			for cx in x.max(0)..(x + width).min(self.width) {
+				coords.push((cx, cy));
+			}
+		}
+		coords.into_boxed_slice()
+	}
 }
 
-impl<T: 'static> Index<<SquareGrid<T> as Space<T>>::Coordinate> for SquareGrid<T> {
+impl<T> Index<(isize, isize)> for SquareGrid<T> {
     type Output = T;
 
-    fn index(&self, index: <SquareGrid<T> as Space<T>>::Coordinate) -> &Self::Output {
+    fn index(&self, index: (isize, isize)) -> &Self::Output {
 		let (x, y) = index;
         &self.cells[(x + y * self.width) as usize]
     }
 }
 
-impl<T: 'static> IndexMut<<SquareGrid<T> as Space<T>>::Coordinate> for SquareGrid<T> {
-	fn index_mut(&mut self, index: <SquareGrid<T> as Space<T>>::Coordinate) -> &mut Self::Output {
+impl<T> IndexMut<(isize, isize)> for SquareGrid<T> {
+	fn index_mut(&mut self, index: (isize, isize)) -> &mut Self::Output {
 		let (x, y) = index;
         &mut self.cells[(x + y * self.width) as usize]
     }
 }
 
-impl<T: 'static> Space<T> for SquareGrid<T> {
+impl<T: ParallelBounds + 'static> Space<T> for SquareGrid<T> {
     type Coordinate = (isize, isize);
 	type CoordinateDelta = (isize, isize);
 
@@ -84,4 +101,9 @@ impl<T: 'static> Space<T> for SquareGrid<T> {
 			}
 		}
     }
+	
+	fn index_of(&self, coord: Self::Coordinate) -> Option<usize> {
+		let (x, y) = coord;
+		Some((x + y * self.width) as usize)
+	}
 }
@@ -1,7 +1,15 @@
+use crate::ParallelBounds;
+
 /// Cell state - represents all possible states a cell can take on
-pub trait State: Clone + PartialEq {
+pub trait State: Clone + PartialEq + ParallelBounds {
 	/// Gets the entropy value of this state. Zero means that the state is
 	/// final, and cannot be collapsed further, while higher values mean there
 	/// are more possible values this state could collapse to.
 	fn entropy(&self) -> u32;
+	/// Checks if this state has no possible values left, meaning the cell
+	/// cannot be resolved under the current rules. States which can never
+	/// become empty don't need to implement this.
+	fn is_contradiction(&self) -> bool {
+		false
+	}
 }
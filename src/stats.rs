@@ -0,0 +1,43 @@
+use std::{ops::AddAssign, time::Duration};
+
+/// Statistics gathered over a run of [crate::collapse]
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct CollapseStats {
+	/// Number of times collapse was started from the initial state of the
+	/// space, including the first
+	pub attempts: usize,
+	/// Number of cells which were observed (forced into a final state)
+	pub observations: usize,
+	/// Number of cells taken off the propagation queue
+	pub propagation_steps: usize,
+	/// Number of observations undone while backtracking
+	pub backtracks: usize,
+	/// Number of calls to [crate::CollapseRule::collapse]
+	pub rule_collapses: usize,
+	/// Number of contradictions found, including those undone by backtracking
+	/// and those which ended an attempt
+	pub contradictions: usize,
+	/// Most cells waiting in the propagation queue at once
+	pub peak_queue_length: usize,
+	/// Time spent choosing which cell to observe next
+	pub selection_time: Duration,
+	/// Time spent observing cells
+	pub observation_time: Duration,
+	/// Time spent propagating changes, including enforcing global constraints
+	pub propagation_time: Duration,
+}
+
+impl AddAssign for CollapseStats {
+	fn add_assign(&mut self, rhs: Self) {
+		self.attempts += rhs.attempts;
+		self.observations += rhs.observations;
+		self.propagation_steps += rhs.propagation_steps;
+		self.backtracks += rhs.backtracks;
+		self.rule_collapses += rhs.rule_collapses;
+		self.contradictions += rhs.contradictions;
+		self.peak_queue_length = self.peak_queue_length.max(rhs.peak_queue_length);
+		self.selection_time += rhs.selection_time;
+		self.observation_time += rhs.observation_time;
+		self.propagation_time += rhs.propagation_time;
+	}
+}
@@ -0,0 +1,135 @@
+use std::ops::{BitOr, BitAnd, BitXor};
+
+use crate::{SetState, State, AllState};
+
+/// A state type which uses bits of an array of u64s to describe more than the
+/// 64 possible final states that fit in a [crate::bitset_state::BitsetState].
+/// 
+/// * `FINAL_STATE_COUNT` - the total number of final (fully collapsed) states
+/// * `WORDS` - the number of u64s to store states in, which must be at least
+///   `FINAL_STATE_COUNT / 64` rounded up
+#[derive(PartialEq, Eq, Copy, Clone, Hash, Debug)]
+pub struct WideBitsetState<const FINAL_STATE_COUNT: u32, const WORDS: usize>([u64; WORDS]);
+
+impl<const FINAL_STATE_COUNT: u32, const WORDS: usize> WideBitsetState<FINAL_STATE_COUNT, WORDS> {
+	/// Creates the `n`th unique state
+	pub const fn state(n: u32) -> Self {
+		let mut words = [0u64; WORDS];
+		words[(n / 64) as usize] = 1u64 << (n % 64);
+		WideBitsetState(words)
+	}
+	
+	/// Creates a state representing the states numbered by members of `states`
+	pub fn with_states(states: &[u32]) -> Self {
+		let mut words = [0u64; WORDS];
+		for i in states {
+			assert!(*i < FINAL_STATE_COUNT);
+			words[(*i / 64) as usize] |= 1u64 << (*i % 64);
+		}
+		WideBitsetState(words)
+	}
+	
+	/// const-fn logical or of all states in `states`
+	pub const fn const_or(states: &[Self]) -> Self {
+		let mut words = [0u64; WORDS];
+		let mut i = 0;
+		while i < states.len() {
+			let mut word = 0;
+			while word < WORDS {
+				words[word] |= states[i].0[word];
+				word += 1;
+			}
+			i += 1;
+		}
+		WideBitsetState(words)
+	}
+	
+	fn zip_with(mut self, rhs: &Self, op: impl Fn(u64, u64) -> u64) -> Self {
+		for (word, rhs_word) in self.0.iter_mut().zip(rhs.0.iter()) {
+			*word = op(*word, *rhs_word);
+		}
+		self
+	}
+}
+
+impl<const FINAL_STATE_COUNT: u32, const WORDS: usize> AllState for WideBitsetState<FINAL_STATE_COUNT, WORDS> {
+	fn all() -> Self {
+		assert!(FINAL_STATE_COUNT as usize <= WORDS * 64 && FINAL_STATE_COUNT >= 1);
+		let mut words = [0u64; WORDS];
+		for (i, word) in words.iter_mut().enumerate() {
+			let remaining = FINAL_STATE_COUNT.saturating_sub(i as u32 * 64);
+			*word = if remaining >= 64 {
+				0xFFFF_FFFF_FFFF_FFFF
+			} else {
+				(1u64 << remaining).wrapping_sub(1)
+			};
+		}
+		WideBitsetState(words)
+	}
+}
+
+impl<const FINAL_STATE_COUNT: u32, const WORDS: usize> State for WideBitsetState<FINAL_STATE_COUNT, WORDS> {
+	fn entropy(&self) -> u32 {
+		self.0.iter().map(|word| word.count_ones()).sum::<u32>().saturating_sub(1)
+	}
+	
+	fn is_contradiction(&self) -> bool {
+		self.is_empty()
+	}
+}
+
+impl<const FINAL_STATE_COUNT: u32, const WORDS: usize> SetState for WideBitsetState<FINAL_STATE_COUNT, WORDS> {
+	fn has_any_of(&self, states: &Self) -> bool {
+		self.0.iter().zip(states.0.iter()).any(|(word, states_word)| word & states_word != 0)
+	}
+	
+	fn clear_states(&mut self, states: &Self) {
+		*self = self.zip_with(states, |word, states_word| word & !states_word);
+	}
+	
+	fn retain_states(&mut self, states: &Self) {
+		*self = self.zip_with(states, |word, states_word| word & states_word);
+	}
+	
+	fn set_states(&mut self, states: &Self) {
+		*self = self.zip_with(states, |word, states_word| word | states_word);
+	}
+	
+	fn collect_final_states(&self, states: &mut Vec<Self>) {
+		for (i, word) in self.0.iter().enumerate() {
+			for bit in 0 .. 64 {
+				if word & (1u64 << bit) != 0 {
+					states.push(Self::state(i as u32 * 64 + bit));
+				}
+			}
+		}
+	}
+	
+	fn is_empty(&self) -> bool {
+		self.0.iter().all(|word| *word == 0)
+	}
+}
+
+impl<const FINAL_STATE_COUNT: u32, const WORDS: usize> BitOr for WideBitsetState<FINAL_STATE_COUNT, WORDS> {
+	type Output = Self;
+	
+	fn bitor(self, rhs: Self) -> Self::Output {
+		self.zip_with(&rhs, |a, b| a | b)
+	}
+}
+
+impl<const FINAL_STATE_COUNT: u32, const WORDS: usize> BitAnd for WideBitsetState<FINAL_STATE_COUNT, WORDS> {
+	type Output = Self;
+	
+	fn bitand(self, rhs: Self) -> Self::Output {
+		self.zip_with(&rhs, |a, b| a & b)
+	}
+}
+
+impl<const FINAL_STATE_COUNT: u32, const WORDS: usize> BitXor for WideBitsetState<FINAL_STATE_COUNT, WORDS> {
+	type Output = Self;
+	
+	fn bitxor(self, rhs: Self) -> Self::Output {
+		self.zip_with(&rhs, |a, b| a ^ b)
+	}
+}
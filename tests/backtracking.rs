@@ -0,0 +1,47 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::square_grid::SquareGrid;
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+
+fn assert_colored(grid: &SquareGrid<S>, size: isize) {
+	for y in 0..size {
+		for x in 0..size {
+			assert_eq!(grid[(x, y)].entropy(), 0);
+			assert!(!grid[(x, y)].is_contradiction());
+			if x + 1 < size {
+				assert_ne!(grid[(x, y)], grid[(x + 1, y)]);
+			}
+			if y + 1 < size {
+				assert_ne!(grid[(x, y)], grid[(x, y + 1)]);
+			}
+		}
+	}
+}
+
+#[test]
+fn test_backtracking_resolves_contradictions() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	for _ in 0..100 {
+		let mut grid = SquareGrid::new(8, 8, |_, _| S::all());
+		let stats = collapse_backtracking(&mut grid, &rule, 10_000).unwrap();
+		assert_colored(&grid, 8);
+		// every contradiction is followed by one more observation undone
+		assert_eq!(stats.contradictions, stats.backtracks);
+	}
+}
+
+#[test]
+fn test_backtracking_exhausted() {
+	// with diagonals, a 2x2 block needs four colors, but propagation alone
+	// can't tell until a cell is observed
+	let rule = coloring_rule(3, &[DIRECTIONS, DIAGONALS].concat());
+	for max_backtracks in [0, 1000] {
+		let mut grid = SquareGrid::new(2, 2, |_, _| S::all());
+		let result = collapse_backtracking(&mut grid, &rule, max_backtracks);
+		assert!(result.is_err());
+	}
+}
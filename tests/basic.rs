@@ -1,6 +1,6 @@
 use kahuna::*;
 use kahuna::square_grid::SquareGrid;
-use rand::{thread_rng, Rng};
+use rand::{Rng, RngCore};
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 enum PossibleStates {
@@ -42,9 +42,9 @@ impl CollapseRule<PossibleStates, TestGrid> for Rule {
 		}
 	}
 	
-	fn observe(&self, cell: &mut PossibleStates, _neighbors: &[Option<PossibleStates>]) {
+	fn observe(&self, cell: &mut PossibleStates, _neighbors: &[Option<PossibleStates>], rng: &mut dyn RngCore) {
 		if let PossibleStates::AB = *cell {
-			*cell = if thread_rng().gen::<bool>() { PossibleStates::A } else { PossibleStates::B };
+			*cell = if rng.gen::<bool>() { PossibleStates::A } else { PossibleStates::B };
 		}
 	}
 }
@@ -52,7 +52,7 @@ impl CollapseRule<PossibleStates, TestGrid> for Rule {
 #[test]
 fn test_basic() {
 	let mut grid = SquareGrid::new(10, 10, |_, _| PossibleStates::AB);
-	collapse(&mut grid, &Rule);
+	collapse(&mut grid, &Rule).unwrap();
 	for y in 0..10 {
 		for x in 0..9 {
 			assert_ne!(grid[(x, y)], PossibleStates::AB);
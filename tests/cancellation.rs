@@ -0,0 +1,168 @@
+use std::time::{Duration, Instant};
+
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+type Grid = SquareGrid<S>;
+
+struct CancelAfter {
+	token: CancellationToken,
+	reductions: usize,
+	interruptions: usize,
+}
+
+impl CollapseListener<S, Grid> for CancelAfter {
+	fn reduced(&mut self, _coord: (isize, isize), _state: &S) {
+		self.reductions = self.reductions.saturating_sub(1);
+		if self.reductions == 0 {
+			self.token.cancel();
+		}
+	}
+	
+	fn interrupted(&mut self) {
+		self.interruptions += 1;
+	}
+}
+
+#[test]
+fn test_cancelled_before_start() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	let mut grid = Grid::new(8, 8, |_, _| S::all());
+	let token = CancellationToken::new();
+	token.cancel();
+	let mut collapser = Collapser::new(&mut grid, &rule).with_cancellation(token);
+	collapser.run().unwrap();
+	assert!(!collapser.is_done());
+	assert!(collapser.is_interrupted());
+	drop(collapser);
+	assert!(grid.coordinate_list().iter().all(|coord| grid[*coord] == S::all()));
+}
+
+#[test]
+fn test_cancelled_during_propagation() {
+	// the first observation decides the whole checkerboard, so cancelling
+	// partway through propagating it leaves the rest undecided
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let initial = S::with_states(&[0, 1]);
+	let mut grid = Grid::new(64, 64, |_, _| initial);
+	let token = CancellationToken::new();
+	let mut listener = CancelAfter { token: token.clone(), reductions: 200, interruptions: 0 };
+	let mut collapser = Collapser::new(&mut grid, &rule)
+		.with_cancellation(token)
+		.with_listener(&mut listener);
+	collapser.run().unwrap();
+	assert!(!collapser.is_done());
+	assert_eq!(collapser.stats().observations, 1);
+	drop(collapser);
+	assert_eq!(listener.interruptions, 1);
+	let undecided = grid.coordinate_list().iter().filter(|coord| grid[**coord] == initial).count();
+	assert!(undecided > 0 && undecided < 64 * 64 - 200);
+	
+	// a new collapser propagates what was left, keeping what was decided
+	let decided: Vec<_> = grid.coordinate_list().iter().copied().filter(|coord| grid[*coord] != initial).collect();
+	let before = cells(&grid);
+	collapse(&mut grid, &rule).unwrap();
+	assert_consistent(&grid, &rule);
+	assert!(decided.iter().all(|coord| grid[*coord] == before[grid.index_of(*coord).unwrap()]));
+}
+
+#[test]
+fn test_deadline_resumes() {
+	// two colors which can't neighbor themselves, and a third which can go
+	// anywhere so that collapse never fails
+	let rule = SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&S::state(0), &DIRECTIONS.map(|delta| (delta, S::with_states(&[1, 2]))))
+		.allow(&S::state(1), &DIRECTIONS.map(|delta| (delta, S::with_states(&[0, 2]))))
+		.allow(&S::state(2), &DIRECTIONS.map(|delta| (delta, S::all())))
+		.build();
+	let mut grid = Grid::new(32, 32, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule)
+		.with_rng(StdRng::seed_from_u64(5))
+		.with_deadline(Instant::now());
+	collapser.run().unwrap();
+	assert_eq!(collapser.stats().observations, 0);
+	while !collapser.is_done() {
+		collapser.set_deadline(Instant::now() + Duration::from_millis(2));
+		collapser.run().unwrap();
+	}
+	assert!(collapser.space().coordinate_list().iter().all(|coord| collapser.space()[*coord].entropy() == 0));
+	assert_consistent(collapser.space(), &rule);
+}
+
+// Runs collapse a tick at a time, giving each tick `tick` to work with.
+// Returns how many ticks it took.
+fn run_ticks<Rule: CollapseRule<S, Grid>>(collapser: &mut Collapser<S, Grid, Rule>, tick: Duration) -> usize {
+	let mut ticks = 0;
+	while !collapser.is_done() {
+		assert!(ticks < 100_000, "collapse isn't getting anywhere");
+		collapser.set_deadline(Instant::now() + tick);
+		collapser.run().unwrap();
+		ticks += 1;
+	}
+	ticks
+}
+
+#[test]
+fn test_deadline_shorter_than_observation() {
+	// the first observation decides the whole checkerboard, which takes many
+	// ticks to propagate
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let mut grid = Grid::new(256, 256, |_, _| S::with_states(&[0, 1]));
+	let mut collapser = Collapser::new(&mut grid, &rule);
+	assert!(run_ticks(&mut collapser, Duration::from_millis(1)) > 1);
+	// the observation is kept rather than made again on every tick
+	assert_eq!(collapser.stats().observations, 1);
+	assert_consistent(collapser.space(), &rule);
+}
+
+#[test]
+fn test_deadline_with_support_propagator() {
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let mut grid = Grid::new(256, 256, |_, _| S::with_states(&[0, 1]));
+	let mut collapser = Collapser::new(&mut grid, &rule).with_propagator(SupportPropagator::new(&rule));
+	assert!(run_ticks(&mut collapser, Duration::from_millis(1)) > 1);
+	assert_eq!(collapser.stats().observations, 1);
+	assert_consistent(collapser.space(), &rule);
+}
+
+#[test]
+fn test_deadline_shorter_than_initial_propagation() {
+	// the corner decides the whole checkerboard during the initial
+	// propagation, which takes far longer than each tick allows
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let initial = S::with_states(&[0, 1]);
+	let mut grid = Grid::new(128, 128, |x, y| if (x, y) == (0, 0) { S::state(0) } else { initial });
+	let mut collapser = Collapser::new(&mut grid, &rule);
+	assert!(run_ticks(&mut collapser, Duration::from_micros(50)) > 1);
+	assert_eq!(collapser.stats().observations, 0);
+	assert_consistent(collapser.space(), &rule);
+}
+
+#[test]
+fn test_cancelled_during_initial_propagation() {
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let initial = S::with_states(&[0, 1]);
+	let corner = |x, y| if (x, y) == (0, 0) { S::state(0) } else { initial };
+	let mut grid = Grid::new(64, 64, corner);
+	let token = CancellationToken::new();
+	let mut listener = CancelAfter { token: token.clone(), reductions: 200, interruptions: 0 };
+	let mut collapser = Collapser::new(&mut grid, &rule)
+		.with_cancellation(token)
+		.with_listener(&mut listener);
+	collapser.run().unwrap();
+	assert!(!collapser.is_done());
+	assert_eq!(collapser.stats().observations, 0);
+	drop(collapser);
+	assert_eq!(listener.interruptions, 1);
+	
+	collapse(&mut grid, &rule).unwrap();
+	assert_consistent(&grid, &rule);
+	assert_eq!(grid[(0, 0)], S::state(0));
+}
@@ -0,0 +1,150 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::square_grid::SquareGrid;
+use std::{cell::RefCell, rc::Rc};
+
+use rand::{SeedableRng, rngs::StdRng};
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+type Coord = (isize, isize);
+
+#[test]
+fn test_rollback_restores_cells() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	let mut grid = SquareGrid::new(8, 8, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule)
+		.with_rng(StdRng::seed_from_u64(3))
+		.with_backtracking(1000);
+	collapser.run_for(5).unwrap();
+	let checkpoint = collapser.checkpoint();
+	let saved = cells(collapser.space());
+	collapser.run().unwrap();
+	assert!(collapser.is_done());
+	assert_ne!(cells(collapser.space()), saved);
+	
+	collapser.rollback(checkpoint).unwrap();
+	assert!(!collapser.is_done());
+	assert_eq!(cells(collapser.space()), saved);
+	collapser.run().unwrap();
+	assert!(cells(collapser.space()).iter().all(|cell| cell.entropy() == 0));
+}
+
+#[test]
+fn test_rollback_before_first_step() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	let mut grid = SquareGrid::new(6, 6, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule)
+		.with_rng(StdRng::seed_from_u64(1))
+		.with_backtracking(1000);
+	let checkpoint = collapser.checkpoint();
+	collapser.pin((2, 2), &S::state(0)).unwrap();
+	collapser.run_for(10).unwrap();
+	collapser.rollback(checkpoint).unwrap();
+	assert!(cells(collapser.space()).iter().all(|cell| *cell == S::all()));
+	collapser.run().unwrap();
+}
+
+#[test]
+fn test_rollback_clears_contradiction() {
+	// searching by hand: undo any step which fails, and try again
+	let rule = coloring_rule(3, &DIRECTIONS);
+	for seed in 0..20 {
+		let mut grid = SquareGrid::new(8, 8, |_, _| S::all());
+		let mut collapser = Collapser::new(&mut grid, &rule).with_rng(StdRng::seed_from_u64(seed));
+		let mut failures = 0;
+		while !collapser.is_done() {
+			let checkpoint = collapser.checkpoint();
+			if collapser.step().is_err() {
+				collapser.rollback(checkpoint).unwrap();
+				failures += 1;
+				assert!(failures < 10_000);
+			}
+			collapser.release(checkpoint);
+		}
+		drop(collapser);
+		for y in 0..8 {
+			for x in 0..8 {
+				assert_eq!(grid[(x, y)].entropy(), 0);
+				if x + 1 < 8 {
+					assert_ne!(grid[(x, y)], grid[(x + 1, y)]);
+				}
+				if y + 1 < 8 {
+					assert_ne!(grid[(x, y)], grid[(x, y + 1)]);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn test_rollback_past_backtracking_is_stale() {
+	let rule = four_color_rule();
+	let mut grid = SquareGrid::new(2, 2, |_, _| S4::all());
+	let mut collapser = Collapser::new(&mut grid, &rule).with_backtracking(16);
+	let (first, observed) = observe_first(&mut collapser);
+	let checkpoint = collapser.checkpoint();
+	restrict_others(&mut collapser, first, observed);
+	let stats = collapser.run().unwrap();
+	assert!(stats.backtracks > 0);
+	assert_eq!(collapser.space()[first], S4::state((observed + 3) % 4));
+	
+	let collapsed = cells(collapser.space());
+	assert_eq!(collapser.rollback(checkpoint), Err(StaleCheckpoint));
+	assert_eq!(cells(collapser.space()), collapsed);
+	assert!(collapser.is_done());
+}
+
+#[test]
+fn test_released_checkpoints() {
+	let rule = four_color_rule();
+	let mut grid = SquareGrid::new(2, 2, |_, _| S4::all());
+	let mut collapser = Collapser::new(&mut grid, &rule).with_backtracking(16);
+	let start = collapser.checkpoint();
+	let (first, observed) = observe_first(&mut collapser);
+	let observation = collapser.checkpoint();
+	collapser.release(start);
+	assert_eq!(collapser.rollback(start), Err(StaleCheckpoint));
+	
+	// the journal is cut down once the last checkpoint is released, but
+	// backtracking can still undo the first observation
+	collapser.release(observation);
+	assert_eq!(collapser.rollback(observation), Err(StaleCheckpoint));
+	restrict_others(&mut collapser, first, observed);
+	let stats = collapser.run().unwrap();
+	assert!(stats.backtracks > 0);
+	assert_eq!(collapser.space()[first], S4::state((observed + 3) % 4));
+}
+
+// Observes the first unresolved cell in coordinate order, noting the last
+// observed cell each time
+struct FirstUnresolved(Rc<RefCell<Vec<Option<Coord>>>>);
+
+impl SelectionHeuristic<S, SquareGrid<S>> for FirstUnresolved {
+	fn select(&mut self, context: &mut SelectionContext<S, SquareGrid<S>>) -> Option<(isize, isize)> {
+		self.0.borrow_mut().push(context.last_observed());
+		context.unresolved().next()
+	}
+}
+
+#[test]
+fn test_rollback_restores_last_observed() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	let mut grid = SquareGrid::new(4, 4, |_, _| S::all());
+	let last_observed = Rc::new(RefCell::new(Vec::new()));
+	let mut collapser = Collapser::new(&mut grid, &rule)
+		.with_rng(StdRng::seed_from_u64(2))
+		.with_heuristic(FirstUnresolved(last_observed.clone()));
+	collapser.run_for(2).unwrap();
+	let checkpoint = collapser.checkpoint();
+	collapser.run_for(3).unwrap();
+	collapser.rollback(checkpoint).unwrap();
+	collapser.step().unwrap();
+	let last_observed = last_observed.borrow();
+	assert_eq!(last_observed.len(), 6);
+	assert!(last_observed[2].is_some());
+	assert_ne!(last_observed[2], last_observed[4]);
+	assert_eq!(last_observed[5], last_observed[2]);
+}
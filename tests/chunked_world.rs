@@ -0,0 +1,69 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::chunked_world::ChunkedWorld;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+type Rule = SetCollapseRule<S, SquareGrid<S>, UniformSetCollapseObserver>;
+
+const CHUNK_SIZE: isize = 8;
+
+// three-coloring, so that chunks which don't match their neighbors would
+// show up as equal colors across the seams
+fn generate(seed: u64) -> ChunkedWorld<S, Rule> {
+	let mut world = ChunkedWorld::new(CHUNK_SIZE, CHUNK_SIZE, coloring_rule(3, &DIRECTIONS), seed, |_, _| S::all())
+		.with_backtracking(10_000)
+		.with_max_attempts(10);
+	for chunk in [(0, 0), (1, 0), (0, 1), (-1, 0), (1, 1), (-1, -1), (0, -1), (-1, 1), (1, -1)] {
+		world.generate_chunk(chunk).unwrap();
+	}
+	world
+}
+
+fn cells(world: &ChunkedWorld<S, Rule>) -> Vec<S> {
+	let mut cells = Vec::new();
+	for y in -CHUNK_SIZE..2 * CHUNK_SIZE {
+		for x in -CHUNK_SIZE..2 * CHUNK_SIZE {
+			cells.push(*world.get(x, y).unwrap());
+		}
+	}
+	cells
+}
+
+#[test]
+fn test_chunks_match_across_seams() {
+	for seed in 0..10 {
+		let world = generate(seed);
+		for y in -CHUNK_SIZE..2 * CHUNK_SIZE {
+			for x in -CHUNK_SIZE..2 * CHUNK_SIZE {
+				let cell = world.get(x, y).unwrap();
+				assert_eq!(cell.entropy(), 0);
+				if let Some(right) = world.get(x + 1, y) {
+					assert_ne!(cell, right);
+				}
+				if let Some(below) = world.get(x, y + 1) {
+					assert_ne!(cell, below);
+				}
+			}
+		}
+		assert!(world.get(2 * CHUNK_SIZE, 0).is_none());
+	}
+}
+
+#[test]
+fn test_chunks_deterministic() {
+	assert_eq!(cells(&generate(3)), cells(&generate(3)));
+	assert_ne!(cells(&generate(3)), cells(&generate(4)));
+}
+
+#[test]
+fn test_chunk_coordinate() {
+	let world = ChunkedWorld::new(CHUNK_SIZE, CHUNK_SIZE, coloring_rule(3, &DIRECTIONS), 0, |_, _| S::all());
+	assert_eq!(world.chunk_coordinate(0, 7), (0, 0));
+	assert_eq!(world.chunk_coordinate(8, -1), (1, -1));
+	assert_eq!(world.chunk_coordinate(-9, -8), (-2, -1));
+}
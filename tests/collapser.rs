@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+type S = BitsetState<3>;
+
+const A: S = S::state(0);
+const B: S = S::state(1);
+const C: S = S::state(2);
+
+const LEFT: (isize, isize) = (-1, 0);
+const UP: (isize, isize) = (0, -1);
+
+fn rule() -> SetCollapseRule<S, SquareGrid<S>, UniformSetCollapseObserver> {
+	SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&A, &[(LEFT, A | B), (UP, A | B | C)])
+		.allow(&B, &[(LEFT, A | B | C), (UP, A | B | C)])
+		.allow(&C, &[(LEFT, B | C), (UP, A | B | C)])
+		.build()
+}
+
+fn resolved_count(grid: &SquareGrid<S>) -> usize {
+	grid.coordinate_list().iter().filter(|coord| grid[**coord].entropy() == 0).count()
+}
+
+#[test]
+fn test_step_by_step() {
+	let rule = rule();
+	let mut grid = SquareGrid::new(10, 10, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule);
+	assert!(!collapser.is_done());
+	
+	let mut last_resolved = 0;
+	let mut steps = 0;
+	while !collapser.is_done() {
+		collapser.step().unwrap();
+		steps += 1;
+		let resolved = resolved_count(collapser.space());
+		assert!(resolved >= last_resolved);
+		last_resolved = resolved;
+	}
+	// the last step only finds that nothing is left to observe
+	assert_eq!(collapser.stats().observations, steps - 1);
+	assert_eq!(last_resolved, 100);
+	
+	collapser.step().unwrap();
+	assert_eq!(collapser.stats().observations, steps - 1);
+}
+
+#[test]
+fn test_run_for_matches_collapse() {
+	let rule = rule();
+	let mut expected = SquareGrid::new(10, 10, |_, _| S::all());
+	collapse_with_rng(&mut expected, &rule, &mut StdRng::seed_from_u64(7)).unwrap();
+	
+	let mut grid = SquareGrid::new(10, 10, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule).with_rng(StdRng::seed_from_u64(7));
+	collapser.run_for(5).unwrap();
+	assert_eq!(collapser.stats().observations, 5);
+	assert!(!collapser.is_done());
+	while !collapser.is_done() {
+		collapser.run_for(5).unwrap();
+	}
+	drop(collapser);
+	for coord in grid.coordinate_list().iter() {
+		assert_eq!(grid[*coord], expected[*coord]);
+	}
+}
+
+#[test]
+fn test_stats() {
+	let rule = rule();
+	let mut grid = SquareGrid::new(10, 10, |_, _| S::all());
+	let stats = collapse_with_rng(&mut grid, &rule, &mut StdRng::seed_from_u64(3)).unwrap();
+	assert_eq!(stats.attempts, 1);
+	assert_eq!(stats.contradictions, 0);
+	assert!(stats.rule_collapses > 0);
+	assert!(stats.rule_collapses <= stats.propagation_steps);
+	// every cell is queued to begin with, and never more than once at a time
+	assert_eq!(stats.peak_queue_length, 100);
+	assert!(stats.propagation_time > Duration::ZERO);
+}
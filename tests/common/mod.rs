@@ -0,0 +1,102 @@
+// Helpers shared between the integration tests. Not every test uses all of
+// them.
+#![allow(dead_code)]
+
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+
+pub type ColoringRule<const N: u32> = SetCollapseRule<BitsetState<N>, SquareGrid<BitsetState<N>>, UniformSetCollapseObserver>;
+
+pub const DIRECTIONS: [(isize, isize); 4] = [
+	(0, -1),
+	(-1, 0),
+	(1, 0),
+	(0, 1),
+];
+
+pub const DIAGONALS: [(isize, isize); 4] = [
+	(-1, -1),
+	(1, -1),
+	(-1, 1),
+	(1, 1),
+];
+
+// Coloring of the grid with `colors` colors, where neighbors in each of
+// `directions` all have different colors. Three colors without diagonals is
+// a rule which plain collapse fails on fairly often.
+pub fn coloring_rule<const N: u32>(colors: u32, directions: &[(isize, isize)]) -> ColoringRule<N> {
+	let all = BitsetState::<N>::with_states(&(0..colors).collect::<Vec<_>>());
+	let mut builder = SetCollapseRuleBuilder::new(UniformSetCollapseObserver);
+	for color in 0..colors {
+		let state = BitsetState::state(color);
+		let neighbors: Vec<_> = directions.iter().map(|delta| (*delta, all ^ state)).collect();
+		builder = builder.allow(&state, &neighbors);
+	}
+	builder.build()
+}
+
+// Every state is allowed next to every other state, so observations never
+// resolve other cells
+pub fn free_rule<const N: u32, O: SetCollapseObserver<BitsetState<N>> + Clone>(observer: O) -> SetCollapseRule<BitsetState<N>, SquareGrid<BitsetState<N>>, O> {
+	let all = BitsetState::<N>::all();
+	SetCollapseRuleBuilder::new(observer)
+		.allow(&all, &[((1, 0), all), ((0, 1), all)])
+		.build()
+}
+
+// Never observes anything, so that running a collapser only propagates and
+// enforces constraints
+pub struct PropagateOnly;
+
+impl<St: State, Sp: Space<St>> SelectionHeuristic<St, Sp> for PropagateOnly {
+	fn select(&mut self, _context: &mut SelectionContext<St, Sp>) -> Option<Sp::Coordinate> {
+		None
+	}
+}
+
+pub fn cells<St: State + 'static>(grid: &SquareGrid<St>) -> Vec<St> {
+	grid.coordinate_list().iter().map(|coord| grid[*coord].clone()).collect()
+}
+
+// checks that collapsing any cell with the rule leaves it as it is, meaning
+// propagation wasn't left partway through
+pub fn assert_consistent<St: State + std::fmt::Debug + 'static, Rule: CollapseRule<St, SquareGrid<St>>>(grid: &SquareGrid<St>, rule: &Rule) {
+	let offsets = rule.neighbor_offsets();
+	let mut neighbors = vec![None; offsets.len()];
+	for coord in grid.coordinate_list().iter() {
+		grid.neighbors(*coord, &offsets, &mut neighbors);
+		let neighbor_states: Vec<_> = neighbors.iter().map(|neighbor| neighbor.map(|neighbor| grid[neighbor].clone())).collect();
+		let mut state = grid[*coord].clone();
+		rule.collapse(&mut state, &neighbor_states);
+		assert_eq!(state, grid[*coord]);
+	}
+}
+
+pub type S4 = BitsetState<4>;
+
+// Every cell of a 2x2 grid with diagonals touches the other three, so with four
+// colors they're all different
+pub fn four_color_rule() -> ColoringRule<4> {
+	coloring_rule(4, &[DIRECTIONS, DIAGONALS].concat())
+}
+
+// Makes the first observation, returning the cell and its color
+pub fn observe_first(collapser: &mut Collapser<S4, SquareGrid<S4>, ColoringRule<4>>) -> ((isize, isize), u32) {
+	collapser.step().unwrap();
+	let first = *collapser.space().coordinate_list().iter().find(|coord| collapser.space()[**coord].entropy() == 0).unwrap();
+	let observed = (0..4).find(|color| collapser.space()[first] == S4::state(*color)).unwrap();
+	(first, observed)
+}
+
+// Keeps the other three cells to the observed color and the two after it,
+// which can't be satisfied until backtracking undoes the first observation.
+// Returns the colors they're kept to.
+pub fn restrict_others(collapser: &mut Collapser<S4, SquareGrid<S4>, ColoringRule<4>>, first: (isize, isize), observed: u32) -> S4 {
+	let allowed = S4::with_states(&[observed, (observed + 1) % 4, (observed + 2) % 4]);
+	for coord in collapser.space().coordinate_list().iter().filter(|coord| **coord != first) {
+		collapser.restrict(*coord, &allowed).unwrap();
+	}
+	allowed
+}
@@ -0,0 +1,233 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::constraints::*;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+mod common;
+use common::{DIRECTIONS, PropagateOnly, assert_consistent};
+
+type S = BitsetState<4>;
+
+const FLOOR: S = S::state(0);
+const WATER: S = S::state(1);
+const SHOP: S = S::state(2);
+const BOSS: S = S::state(3);
+
+const LEFT: (isize, isize) = (-1, 0);
+const RIGHT: (isize, isize) = (1, 0);
+
+// anything can go next to anything, except that shops and bosses need floor around them
+fn rule() -> SetCollapseRule<S, SquareGrid<S>, UniformSetCollapseObserver> {
+	let rooms = |allowed: S| DIRECTIONS.iter().map(|delta| (*delta, allowed)).collect::<Vec<_>>();
+	SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&FLOOR, &rooms(S::all()))
+		.allow(&WATER, &rooms(FLOOR | WATER))
+		.allow(&SHOP, &rooms(FLOOR))
+		.allow(&BOSS, &rooms(FLOOR))
+		.build()
+}
+
+fn count(grid: &SquareGrid<S>, state: S) -> usize {
+	grid.coordinate_list().iter().filter(|coord| grid[**coord] == state).count()
+}
+
+#[test]
+fn test_count_constraints() {
+	let rule = rule();
+	for seed in 0..20 {
+		let mut grid = SquareGrid::new(10, 10, |_, _| S::all());
+		Collapser::new(&mut grid, &rule)
+			.with_rng(StdRng::seed_from_u64(seed))
+			.with_constraint(CountConstraint::exactly(BOSS, 1))
+			.with_constraint(CountConstraint::at_most(SHOP, 3))
+			.with_constraint(CountConstraint::at_least(WATER, 10))
+			.with_backtracking(1000)
+			.run()
+			.unwrap();
+		assert_eq!(count(&grid, BOSS), 1);
+		assert!(count(&grid, SHOP) <= 3);
+		assert!(count(&grid, WATER) >= 10);
+	}
+}
+
+#[test]
+fn test_minimum_forces_cells() {
+	// most cells would be floor, so reaching the minimum needs forcing
+	let rule = rule();
+	for seed in 0..20 {
+		let mut grid = SquareGrid::new(5, 5, |_, _| S::all());
+		Collapser::new(&mut grid, &rule)
+			.with_rng(StdRng::seed_from_u64(seed))
+			.with_constraint(CountConstraint::at_least(SHOP, 9))
+			.with_backtracking(1000)
+			.run()
+			.unwrap();
+		assert!(count(&grid, SHOP) >= 9);
+	}
+}
+
+#[test]
+fn test_unsatisfiable_count() {
+	let rule = rule();
+	let mut grid = SquareGrid::new(2, 2, |_, _| S::all());
+	let result = Collapser::new(&mut grid, &rule)
+		.with_constraint(CountConstraint::at_least(BOSS, 5))
+		.run();
+	assert!(result.is_err());
+}
+
+#[test]
+fn test_count_on_empty_space() {
+	let rule = rule();
+	let mut grid = SquareGrid::new(0, 0, |_, _| S::all());
+	let stats = Collapser::new(&mut grid, &rule)
+		.with_constraint(CountConstraint::at_least(BOSS, 1))
+		.run()
+		.unwrap();
+	assert_eq!(stats.observations, 0);
+}
+
+fn connected(grid: &SquareGrid<S>, passable: S, size: isize) -> bool {
+	let cells: Vec<_> = grid.coordinate_list().iter().copied().filter(|coord| grid[*coord] == passable).collect();
+	let Some(start) = cells.first() else {
+		return true;
+	};
+	let mut seen = std::collections::HashSet::from([*start]);
+	let mut stack = vec![*start];
+	while let Some((x, y)) = stack.pop() {
+		for (dx, dy) in DIRECTIONS {
+			let next = (x + dx, y + dy);
+			if next.0 >= 0 && next.1 >= 0 && next.0 < size && next.1 < size && grid[next] == passable && seen.insert(next) {
+				stack.push(next);
+			}
+		}
+	}
+	seen.len() == cells.len()
+}
+
+#[test]
+fn test_connected_floor() {
+	// water can't be next to shops or bosses, so only plain floor counts
+	let rule = rule();
+	for seed in 0..20 {
+		let mut grid = SquareGrid::new(12, 12, |_, _| S::all());
+		Collapser::new(&mut grid, &rule)
+			.with_rng(StdRng::seed_from_u64(seed))
+			.with_constraint(ConnectivityConstraint::new(FLOOR, &DIRECTIONS))
+			.with_backtracking(1000)
+			.run()
+			.unwrap();
+		assert!(connected(&grid, FLOOR, 12));
+	}
+}
+
+#[test]
+fn test_required_path() {
+	let rule = rule();
+	for seed in 0..20 {
+		let mut grid = SquareGrid::new(12, 12, |_, _| S::all());
+		Collapser::new(&mut grid, &rule)
+			.with_rng(StdRng::seed_from_u64(seed))
+			.with_constraint(ConnectivityConstraint::new(WATER, &DIRECTIONS).with_required(&[(0, 0), (11, 11)]))
+			.with_backtracking(1000)
+			.run()
+			.unwrap();
+		assert_eq!(grid[(0, 0)], WATER);
+		assert_eq!(grid[(11, 11)], WATER);
+		assert!(connected(&grid, WATER, 12));
+	}
+}
+
+#[test]
+fn test_unreachable_requirement() {
+	let rule = rule();
+	let mut grid = SquareGrid::new(4, 4, |x, _| if x == 2 { SHOP } else { S::all() });
+	let result = Collapser::new(&mut grid, &rule)
+		.with_constraint(ConnectivityConstraint::new(WATER, &DIRECTIONS).with_required(&[(0, 0), (3, 3)]))
+		.run();
+	assert!(result.is_err());
+}
+
+#[test]
+fn test_requirement_outside_space() {
+	let rule = rule();
+	let mut grid = SquareGrid::new(4, 4, |_, _| S::all());
+	let contradiction = Collapser::new(&mut grid, &rule)
+		.with_constraint(ConnectivityConstraint::new(WATER, &DIRECTIONS).with_required(&[(0, 0), (9, 9)]))
+		.run()
+		.unwrap_err();
+	assert_eq!(contradiction.coordinate, (9, 9));
+}
+
+#[test]
+fn test_restrictions_keep_propagated_changes() {
+	// in a row starting with a shop, limiting shops to one takes them out of
+	// both other cells. Taking the shop out of the middle cell then takes the
+	// boss out of the last cell, as bosses only go right of a shop, which
+	// restricting the last cell mustn't undo.
+	let rule = SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&FLOOR, &[(LEFT, FLOOR | SHOP | BOSS), (RIGHT, FLOOR | SHOP)])
+		.allow(&SHOP, &[(LEFT, FLOOR | SHOP | BOSS), (RIGHT, FLOOR | SHOP | BOSS)])
+		.allow(&BOSS, &[(LEFT, SHOP), (RIGHT, FLOOR | SHOP)])
+		.build();
+	let row = |x: isize, _| if x == 0 { SHOP } else { FLOOR | SHOP | BOSS };
+	for support_counting in [false, true] {
+		let mut grid = SquareGrid::new(3, 1, row);
+		let mut collapser = Collapser::new(&mut grid, &rule)
+			.with_heuristic(PropagateOnly)
+			.with_constraint(CountConstraint::at_most(SHOP, 1));
+		if support_counting {
+			collapser = collapser.with_propagator(SupportPropagator::new(&rule));
+		}
+		collapser.run().unwrap();
+		assert_eq!(collapser.space()[(1, 0)], FLOOR | BOSS);
+		assert_eq!(collapser.space()[(2, 0)], FLOOR);
+		
+		for seed in 0..200 {
+			let mut grid = SquareGrid::new(3, 1, row);
+			let mut collapser = Collapser::new(&mut grid, &rule)
+				.with_rng(StdRng::seed_from_u64(seed))
+				.with_constraint(CountConstraint::at_most(SHOP, 1));
+			if support_counting {
+				collapser = collapser.with_propagator(SupportPropagator::new(&rule));
+			}
+			collapser.run().unwrap();
+			assert_eq!(count(collapser.space(), SHOP), 1);
+			assert_consistent(collapser.space(), &rule);
+		}
+	}
+}
+
+#[test]
+fn test_constraints_keep_each_others_restrictions() {
+	// both constraints restrict both other cells in the same check, and
+	// neither may bring back what the other took out
+	let rule = SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&S::all(), &[(LEFT, S::all()), (RIGHT, S::all())])
+		.build();
+	let passable = FLOOR | SHOP | BOSS;
+	let row = |x: isize, _| if x == 0 { SHOP } else { S::all() };
+	let mut grid = SquareGrid::new(3, 1, row);
+	Collapser::new(&mut grid, &rule)
+		.with_heuristic(PropagateOnly)
+		.with_constraint(CountConstraint::at_most(SHOP, 1))
+		.with_constraint(ConnectivityConstraint::new(passable, &[LEFT, RIGHT]).with_required(&[(0, 0), (2, 0)]))
+		.run()
+		.unwrap();
+	assert_eq!(grid[(1, 0)], FLOOR | BOSS);
+	assert_eq!(grid[(2, 0)], FLOOR | BOSS);
+	
+	for seed in 0..200 {
+		let mut grid = SquareGrid::new(3, 1, row);
+		Collapser::new(&mut grid, &rule)
+			.with_rng(StdRng::seed_from_u64(seed))
+			.with_constraint(CountConstraint::at_most(SHOP, 1))
+			.with_constraint(ConnectivityConstraint::new(passable, &[LEFT, RIGHT]).with_required(&[(0, 0), (2, 0)]))
+			.run()
+			.unwrap();
+		assert_eq!(count(&grid, SHOP), 1);
+		assert!(grid.coordinate_list().iter().all(|coord| passable.has_any_of(&grid[*coord])));
+	}
+}
@@ -0,0 +1,36 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+
+type S = BitsetState<2>;
+
+const A: S = S::state(0);
+const B: S = S::state(1);
+
+const UP: (isize, isize) = (0, -1);
+
+#[test]
+fn test_contradiction_reported() {
+	let rule = SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&A, &[(UP, A)])
+		.build();
+	let mut grid = SquareGrid::new(1, 2, |_, y| if y == 0 { B } else { A | B });
+	let contradiction = collapse(&mut grid, &rule).unwrap_err();
+	assert_eq!(contradiction.coordinate, (0, 1));
+	assert!(contradiction.neighbors.contains(&Some(B)));
+	assert!(grid[(0, 1)].is_contradiction());
+}
+
+#[test]
+fn test_satisfiable_has_no_contradiction() {
+	let rule = SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&A, &[(UP, A)])
+		.build();
+	let mut grid = SquareGrid::new(1, 4, |_, _| A | B);
+	let stats = collapse(&mut grid, &rule).unwrap();
+	for y in 0..4 {
+		assert_eq!(grid[(0, y)], A);
+	}
+	assert_eq!(stats.observations, 0);
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "derive")]
+
+use kahuna::*;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+
+mod common;
+use common::*;
+
+#[derive(Tiles, Clone, Copy, PartialEq, Eq, Debug)]
+enum Tile {
+	Grass,
+	Sand,
+	Water,
+}
+
+// more tiles than fit in one u64
+#[derive(Tiles, Clone, Copy, PartialEq, Eq, Debug)]
+enum Many {
+	T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16, T17, T18, T19, T20, T21, T22, T23, T24, T25, T26, T27, T28, T29, T30, T31, T32, T33, T34, T35, T36, T37, T38, T39, T40, T41, T42, T43, T44, T45, T46, T47, T48, T49, T50, T51, T52, T53, T54, T55, T56, T57, T58, T59, T60, T61, T62, T63, T64, T65, T66, T67, T68, T69
+}
+
+#[test]
+fn test_tile_set() {
+	let set = Tile::Grass | Tile::Water;
+	assert!(set.contains(&Tile::Grass));
+	assert!(!set.contains(&Tile::Sand));
+	assert_eq!(set.tiles().collect::<Vec<_>>(), [Tile::Grass, Tile::Water]);
+	assert_eq!(set.entropy(), 1);
+	assert_eq!(set.tile(), None);
+	assert_eq!(Tile::try_from(set), Err(set));
+	assert_eq!(set.to_string(), "{Grass, Water}");
+	
+	let sand = TileSet::from(Tile::Sand);
+	assert_eq!(sand.tile(), Some(Tile::Sand));
+	assert_eq!(Tile::try_from(sand), Ok(Tile::Sand));
+	assert_eq!(sand.to_string(), "Sand");
+	assert_eq!(TileSet::all(), set | Tile::Sand);
+	assert_eq!(TileSet::all() ^ set, sand);
+	assert_eq!(TileSet::with_tiles(&[Tile::Sand, Tile::Water]) & set, TileSet::new(Tile::Water));
+	assert_eq!(TileSet::TILES, [Tile::Grass, Tile::Sand, Tile::Water]);
+}
+
+#[test]
+fn test_wide_tile_set() {
+	let all = ManySet::all();
+	assert_eq!(all.entropy(), 69);
+	assert!(all.contains(&Many::T69));
+	assert_eq!((Many::T0 | Many::T65).tiles().collect::<Vec<_>>(), [Many::T0, Many::T65]);
+	assert_eq!(ManySet::new(Many::T64).to_string(), "T64");
+}
+
+#[test]
+fn test_collapse_tiles() {
+	// sand has to be between grass and water
+	let rule = SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&Tile::Grass.into(), &DIRECTIONS.map(|delta| (delta, Tile::Grass | Tile::Sand)))
+		.allow(&Tile::Sand.into(), &DIRECTIONS.map(|delta| (delta, TileSet::all())))
+		.allow(&Tile::Water.into(), &DIRECTIONS.map(|delta| (delta, Tile::Sand | Tile::Water)))
+		.build();
+	let mut grid = SquareGrid::new(10, 10, |_, _| TileSet::all());
+	collapse(&mut grid, &rule).unwrap();
+	for y in 0..10 {
+		for x in 0..9 {
+			let tiles = [Tile::try_from(grid[(x, y)]).unwrap(), Tile::try_from(grid[(x + 1, y)]).unwrap()];
+			assert!(tiles != [Tile::Grass, Tile::Water] && tiles != [Tile::Water, Tile::Grass]);
+		}
+	}
+}
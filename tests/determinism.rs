@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+type S = BitsetState<4>;
+
+const A: S = S::state(0);
+const B: S = S::state(1);
+const C: S = S::state(2);
+const D: S = S::state(3);
+
+const UP: (isize, isize) = (0, -1);
+const LEFT: (isize, isize) = (-1, 0);
+
+fn rule() -> SetCollapseRule<S, SquareGrid<S>, UniformSetCollapseObserver> {
+	SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&A, &[(UP, A | B | C), (LEFT, A | B | D)])
+		.allow(&B, &[(UP, A | C | D), (LEFT, B | C)])
+		.allow(&C, &[(UP, B | C | D), (LEFT, A | C | D)])
+		.allow(&D, &[(UP, A | D), (LEFT, B | C | D)])
+		.build()
+}
+
+// contradictions are part of the output too - they should also be reproducible
+fn generate(seed: u64, backtracking: bool) -> (Vec<S>, CollapseResult<S, SquareGrid<S>>) {
+	let rule = rule();
+	let mut grid = SquareGrid::new(16, 16, |_, _| S::all());
+	let mut rng = StdRng::seed_from_u64(seed);
+	let result = if backtracking {
+		collapse_backtracking_with_rng(&mut grid, &rule, 1000, &mut rng)
+	} else {
+		collapse_with_rng(&mut grid, &rule, &mut rng)
+	};
+	// everything apart from how long it took
+	let result = result.map(|stats| CollapseStats {
+		selection_time: Duration::ZERO,
+		observation_time: Duration::ZERO,
+		propagation_time: Duration::ZERO,
+		..stats
+	});
+	(grid.coordinate_list().iter().map(|coord| grid[*coord]).collect(), result)
+}
+
+#[test]
+fn test_same_seed_same_output() {
+	for seed in 0..10 {
+		assert_eq!(generate(seed, false), generate(seed, false));
+		assert_eq!(generate(seed, true), generate(seed, true));
+	}
+}
+
+#[test]
+fn test_different_seed_different_output() {
+	assert_ne!(generate(1, false).0, generate(2, false).0);
+}
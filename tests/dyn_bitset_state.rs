@@ -0,0 +1,51 @@
+use kahuna::*;
+use kahuna::dyn_bitset_state::*;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+
+const LEFT: (isize, isize) = (-1, 0);
+const UP: (isize, isize) = (0, -1);
+
+#[test]
+fn test_states() {
+	let universe = DynBitsetUniverse::new(100);
+	let all = universe.all();
+	assert_eq!(all.entropy(), 99);
+	assert_eq!(universe.empty().entropy(), 0);
+	assert!(universe.empty().is_contradiction());
+	assert_eq!(&universe.state(3) | &universe.state(70), universe.with_states(&[3, 70]));
+	assert_eq!(&all ^ &universe.with_states(&(1..100).collect::<Vec<_>>()), universe.state(0));
+	
+	let mut state = all.clone();
+	state.retain_states(&universe.with_states(&[10, 64, 99]));
+	state.clear_states(&universe.state(64));
+	assert_eq!(state, universe.state(10) | universe.state(99));
+	
+	let mut finals = Vec::new();
+	state.collect_final_states(&mut finals);
+	assert_eq!(finals, vec![universe.state(10), universe.state(99)]);
+}
+
+#[test]
+fn test_collapse_loaded_tileset() {
+	// stands in for a tile count and adjacency list read from a mod file
+	let tile_count = 75;
+	let universe = DynBitsetUniverse::new(tile_count);
+	let mut builder = SetCollapseRuleBuilder::new(UniformSetCollapseObserver);
+	for tile in 0..tile_count {
+		// tiles of the same parity can sit next to each other
+		let matching = universe.with_states(&(0..tile_count).filter(|other| other % 2 == tile % 2).collect::<Vec<_>>());
+		builder = builder.allow(&universe.state(tile), &[(LEFT, matching.clone()), (UP, matching)]);
+	}
+	let rule = builder.build_from(&universe.all());
+	let all = universe.all();
+	let mut grid = SquareGrid::new(12, 12, |_, _| all.clone());
+	collapse(&mut grid, &rule).unwrap();
+	
+	let even = universe.with_states(&(0..tile_count).step_by(2).collect::<Vec<_>>());
+	let first_even = grid[(0, 0)].has_any_of(&even);
+	for coord in grid.coordinate_list().iter() {
+		assert_eq!(grid[*coord].entropy(), 0);
+		assert_eq!(grid[*coord].has_any_of(&even), first_even);
+	}
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeSet;
+
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::heuristics::*;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+
+const RIGHT: (isize, isize) = (1, 0);
+const DOWN: (isize, isize) = (0, 1);
+
+fn resolved(grid: &SquareGrid<S>) -> BTreeSet<(isize, isize)> {
+	grid.coordinate_list().iter().copied().filter(|coord| grid[*coord].entropy() == 0).collect()
+}
+
+#[test]
+fn test_scanline() {
+	let rule = free_rule(UniformSetCollapseObserver);
+	let mut grid = SquareGrid::new(6, 4, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule).with_heuristic(Scanline::new());
+	for step in 0..24 {
+		collapser.step().unwrap();
+		let resolved = resolved(collapser.space());
+		assert_eq!(resolved.len(), step + 1);
+		for (x, y) in resolved {
+			assert!((x + y * 6) as usize <= step);
+		}
+	}
+	collapser.step().unwrap();
+	assert!(collapser.is_done());
+}
+
+#[test]
+fn test_random_cell() {
+	let rule = free_rule(UniformSetCollapseObserver);
+	let mut grid = SquareGrid::new(6, 4, |_, _| S::all());
+	let stats = Collapser::new(&mut grid, &rule).with_heuristic(RandomCell::new()).run().unwrap();
+	assert_eq!(stats.observations, 24);
+	assert_eq!(resolved(&grid).len(), 24);
+}
+
+#[test]
+fn test_growth_stays_connected() {
+	let rule = free_rule(UniformSetCollapseObserver);
+	let mut grid = SquareGrid::new(10, 10, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule).with_heuristic(Growth);
+	for _ in 0..30 {
+		collapser.step().unwrap();
+		let resolved = resolved(collapser.space());
+		let start = *resolved.iter().next().unwrap();
+		let mut reached = BTreeSet::from([start]);
+		let mut frontier = vec![start];
+		while let Some((x, y)) = frontier.pop() {
+			for neighbor in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+				if resolved.contains(&neighbor) && reached.insert(neighbor) {
+					frontier.push(neighbor);
+				}
+			}
+		}
+		assert_eq!(reached, resolved);
+	}
+}
+
+// checks that every cell picked by MinimumEntropy really has the lowest
+// entropy, while passing updates through to it
+struct CheckedMinimumEntropy(MinimumEntropy<(isize, isize)>);
+
+impl SelectionHeuristic<S, SquareGrid<S>> for CheckedMinimumEntropy {
+	fn select(&mut self, context: &mut SelectionContext<S, SquareGrid<S>>) -> Option<(isize, isize)> {
+		let selected = self.0.select(context)?;
+		let lowest = context.unresolved().map(|coord| context.space()[coord].entropy()).min().unwrap();
+		assert_eq!(context.space()[selected].entropy(), lowest);
+		Some(selected)
+	}
+	
+	fn update(&mut self, coord: (isize, isize), context: &mut SelectionContext<S, SquareGrid<S>>) {
+		self.0.update(coord, context);
+	}
+	
+	fn reset(&mut self) {
+		SelectionHeuristic::<S, SquareGrid<S>>::reset(&mut self.0);
+	}
+}
+
+#[test]
+fn test_minimum_entropy() {
+	let rule = SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&S::state(0), &[(RIGHT, S::state(1) | S::state(2)), (DOWN, S::state(1) | S::state(2))])
+		.allow(&S::state(1), &[(RIGHT, S::state(2) | S::state(0)), (DOWN, S::state(2))])
+		.allow(&S::state(2), &[(RIGHT, S::state(0) | S::state(1)), (DOWN, S::state(0))])
+		.build();
+	let mut grid = SquareGrid::new(16, 16, |_, _| S::all());
+	let stats = Collapser::new(&mut grid, &rule)
+		.with_heuristic(CheckedMinimumEntropy(MinimumEntropy::new()))
+		.with_backtracking(1000)
+		.run()
+		.unwrap();
+	assert!(stats.observations > 0);
+	assert_eq!(resolved(&grid).len(), 256);
+}
+
+#[test]
+fn test_minimum_entropy_skips_stale_entries() {
+	let rule = free_rule(UniformSetCollapseObserver);
+	let mut grid = SquareGrid::new(4, 4, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule).with_heuristic(CheckedMinimumEntropy(MinimumEntropy::new()));
+	collapser.step().unwrap();
+	let unresolved: Vec<_> = collapser.space().coordinate_list().iter().copied().filter(|coord| collapser.space()[*coord].entropy() > 0).collect();
+	let (moved, picked) = (unresolved[0], unresolved[1]);
+	// `moved` goes down a bucket and then resolves, leaving entries for it
+	// behind in both of the buckets it was in
+	collapser.restrict(moved, &(S::state(0) | S::state(1))).unwrap();
+	collapser.pin(moved, &S::state(0)).unwrap();
+	collapser.restrict(picked, &(S::state(1) | S::state(2))).unwrap();
+	let resolved_before = resolved(collapser.space());
+	collapser.step().unwrap();
+	let newly_resolved: Vec<_> = resolved(collapser.space()).difference(&resolved_before).copied().collect();
+	assert_eq!(newly_resolved, [picked]);
+	collapser.run().unwrap();
+}
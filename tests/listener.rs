@@ -0,0 +1,75 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::square_grid::SquareGrid;
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+type Grid = SquareGrid<S>;
+
+#[derive(Default)]
+struct Recorder {
+	observed: Vec<((isize, isize), S)>,
+	reduced: Vec<((isize, isize), S)>,
+	progress: Vec<usize>,
+	contradictions: Vec<(isize, isize)>,
+	completed: Option<CollapseStats>,
+}
+
+impl CollapseListener<S, Grid> for Recorder {
+	fn observed(&mut self, coord: (isize, isize), state: &S) {
+		self.observed.push((coord, *state));
+	}
+	
+	fn reduced(&mut self, coord: (isize, isize), state: &S) {
+		self.reduced.push((coord, *state));
+	}
+	
+	fn progress(&mut self, unresolved_count: usize) {
+		self.progress.push(unresolved_count);
+	}
+	
+	fn contradiction(&mut self, contradiction: &Contradiction<(isize, isize), S>) {
+		self.contradictions.push(contradiction.coordinate);
+	}
+	
+	fn completed(&mut self, stats: &CollapseStats) {
+		self.completed = Some(*stats);
+	}
+}
+
+#[test]
+fn test_listener_events() {
+	// two colors which can't neighbor themselves, so one observation decides
+	// the whole grid
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let all = S::with_states(&[0, 1]);
+	let mut grid = Grid::new(4, 4, |_, _| all);
+	let mut recorder = Recorder::default();
+	let stats = Collapser::new(&mut grid, &rule).with_listener(&mut recorder).run().unwrap();
+	
+	assert_eq!(recorder.observed.len(), 1);
+	let (observed_coord, observed_state) = recorder.observed[0];
+	assert_eq!(grid[observed_coord], observed_state);
+	// every other cell is decided by propagation
+	assert_eq!(recorder.reduced.len(), 15);
+	for (coord, state) in &recorder.reduced {
+		assert_eq!(grid[*coord], *state);
+	}
+	assert_eq!(recorder.progress, vec![0]);
+	assert!(recorder.contradictions.is_empty());
+	assert_eq!(recorder.completed, Some(stats));
+}
+
+#[test]
+fn test_listener_contradiction() {
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let all = S::with_states(&[0, 1]);
+	// neighboring cells of the same color
+	let mut grid = Grid::new(3, 1, |x, _| if x == 1 { all } else if x == 0 { S::state(0) } else { S::state(1) });
+	let mut recorder = Recorder::default();
+	let contradiction = Collapser::new(&mut grid, &rule).with_listener(&mut recorder).run().unwrap_err();
+	assert_eq!(recorder.contradictions, vec![contradiction.coordinate]);
+	assert_eq!(recorder.completed, None);
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "parallel")]
+
+use std::rc::Rc;
+
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+mod common;
+use common::*;
+
+type S = BitsetState<4>;
+
+// large enough that propagation batches are split between threads
+const SIZE: isize = 64;
+
+fn generate(seed: u64) -> Vec<S> {
+	let rule = coloring_rule(4, &DIRECTIONS);
+	let mut grid = SquareGrid::new(SIZE, SIZE, |x, y| if x == y { S::state(0) } else { S::all() });
+	collapse_backtracking_with_rng(&mut grid, &rule, 10_000, &mut StdRng::seed_from_u64(seed)).unwrap();
+	for y in 0..SIZE {
+		for x in 0..SIZE {
+			assert_eq!(grid[(x, y)].entropy(), 0);
+			if x + 1 < SIZE {
+				assert_ne!(grid[(x, y)], grid[(x + 1, y)]);
+			}
+			if y + 1 < SIZE {
+				assert_ne!(grid[(x, y)], grid[(x, y + 1)]);
+			}
+		}
+	}
+	cells(&grid)
+}
+
+#[test]
+fn test_parallel_propagation_deterministic() {
+	for seed in 0..4 {
+		assert_eq!(generate(seed), generate(seed));
+	}
+}
+
+#[test]
+fn test_grid_indexing_without_parallel_bounds() {
+	// indexing shouldn't need the cells to be shared between threads, since
+	// only collapse does that
+	let mut grid = SquareGrid::new(2, 2, |x, y| Rc::new(x + y * 2));
+	grid[(1, 1)] = Rc::new(7);
+	assert_eq!(*grid[(0, 1)], 2);
+	assert_eq!(*grid[(1, 1)], 7);
+}
@@ -0,0 +1,164 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Mutex, atomic::{AtomicUsize, Ordering}};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::square_grid::SquareGrid;
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+
+mod common;
+use common::*;
+
+type S = BitsetState<5>;
+type Grid = SquareGrid<S>;
+
+const SIZE: isize = 24;
+
+const ATTEMPTS: usize = 8;
+
+fn three_colors() -> S {
+	S::with_states(&[0, 1, 2])
+}
+
+// Wraps a rule to treat the first attempt to make an observation differently
+// from the others, which are told apart by the thread they run on
+struct FirstAttempt<'r> {
+	rule: &'r ColoringRule<5>,
+	first: Mutex<Option<ThreadId>>,
+	// observations made by every attempt apart from the first
+	other_observations: AtomicUsize,
+	// slows down every attempt apart from the first
+	delay: Duration,
+	// slows down propagation after the first observation of every attempt
+	// apart from the first
+	propagation_delay: Duration,
+	// threads of the other attempts which have made an observation
+	other_observers: Mutex<Vec<ThreadId>>,
+	// collapses slowed down by `propagation_delay`
+	delayed_collapses: AtomicUsize,
+	// makes the first attempt panic
+	panic: bool,
+}
+
+impl<'r> FirstAttempt<'r> {
+	fn new(rule: &'r ColoringRule<5>) -> Self {
+		Self {
+			rule,
+			first: Mutex::new(None),
+			other_observations: AtomicUsize::new(0),
+			delay: Duration::ZERO,
+			propagation_delay: Duration::ZERO,
+			other_observers: Mutex::new(Vec::new()),
+			delayed_collapses: AtomicUsize::new(0),
+			panic: false,
+		}
+	}
+}
+
+impl CollapseRule<S, Grid> for FirstAttempt<'_> {
+	fn neighbor_offsets(&self) -> Box<[(isize, isize)]> {
+		self.rule.neighbor_offsets()
+	}
+
+	fn collapse(&self, cell: &mut S, neighbors: &[Option<S>]) {
+		if self.other_observers.lock().unwrap().contains(&thread::current().id()) {
+			self.delayed_collapses.fetch_add(1, Ordering::Relaxed);
+			thread::sleep(self.propagation_delay);
+		}
+		self.rule.collapse(cell, neighbors);
+	}
+
+	fn observe(&self, cell: &mut S, neighbors: &[Option<S>], rng: &mut dyn RngCore) {
+		let thread = thread::current().id();
+		if *self.first.lock().unwrap().get_or_insert(thread) == thread {
+			if self.panic {
+				panic!("attempt failed");
+			}
+		} else {
+			self.other_observations.fetch_add(1, Ordering::Relaxed);
+			if self.propagation_delay > Duration::ZERO {
+				self.other_observers.lock().unwrap().push(thread);
+			}
+			thread::sleep(self.delay);
+		}
+		self.rule.observe(cell, neighbors, rng);
+	}
+}
+
+#[test]
+fn test_racing_keeps_a_successful_attempt() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	for seed in 0..4 {
+		let mut grid = Grid::new(SIZE, SIZE, |_, _| three_colors());
+		collapse_racing_seeded(&mut grid, &rule, ATTEMPTS, seed).unwrap();
+		// the result should be exactly what one of the attempts gives when run
+		// on its own
+		let won = (0..ATTEMPTS).any(|attempt| {
+			let mut expected = Grid::new(SIZE, SIZE, |_, _| three_colors());
+			let mut rng = StdRng::seed_from_u64(derive_seed(seed, attempt as u64));
+			collapse_with_rng(&mut expected, &rule, &mut rng).is_ok() && cells(&expected) == cells(&grid)
+		});
+		assert!(won);
+	}
+}
+
+#[test]
+fn test_racing_stops_other_attempts() {
+	// with five colors a cell always has a color left, so the first attempt
+	// always succeeds, long before the slowed down attempts could get through
+	// the grid
+	let five_colors = coloring_rule(5, &DIRECTIONS);
+	let rule = FirstAttempt {
+		delay: Duration::from_millis(5),
+		..FirstAttempt::new(&five_colors)
+	};
+	let mut grid = Grid::new(SIZE, SIZE, |_, _| S::all());
+	collapse_racing_seeded(&mut grid, &rule, ATTEMPTS, 0).unwrap();
+	assert!(cells(&grid).iter().all(|cell| cell.entropy() == 0));
+	// each of the others stops within a step of the first one finishing
+	assert!(rule.other_observations.load(Ordering::Relaxed) < (SIZE * SIZE) as usize / 4);
+}
+
+#[test]
+fn test_racing_stops_other_attempts_while_propagating() {
+	// with two colors on a line, the first observation decides every cell,
+	// which propagation goes through one at a time
+	const LENGTH: isize = 2000;
+	let two_colors = coloring_rule(2, &DIRECTIONS);
+	let rule = FirstAttempt {
+		propagation_delay: Duration::from_millis(1),
+		..FirstAttempt::new(&two_colors)
+	};
+	let mut grid = Grid::new(LENGTH, 1, |_, _| S::with_states(&[0, 1]));
+	collapse_racing_seeded(&mut grid, &rule, ATTEMPTS, 0).unwrap();
+	assert!(cells(&grid).iter().all(|cell| cell.entropy() == 0));
+	// each of the others stops partway through propagating its observation
+	assert!(rule.delayed_collapses.load(Ordering::Relaxed) < (ATTEMPTS - 1) * LENGTH as usize / 4);
+}
+
+#[test]
+fn test_racing_leaves_space_when_every_attempt_fails() {
+	// with diagonals every 2x2 block needs four colors, so every attempt runs
+	// into a contradiction after making some observations
+	let rule = coloring_rule(3, &[DIRECTIONS, DIAGONALS].concat());
+	let mut grid = Grid::new(6, 6, |_, _| three_colors());
+	let before = cells(&grid);
+	for seed in 0..4 {
+		collapse_racing_seeded(&mut grid, &rule, ATTEMPTS, seed).unwrap_err();
+		assert_eq!(cells(&grid), before);
+	}
+}
+
+#[test]
+fn test_racing_propagates_panics() {
+	let five_colors = coloring_rule(5, &DIRECTIONS);
+	let rule = FirstAttempt {
+		panic: true,
+		..FirstAttempt::new(&five_colors)
+	};
+	let mut grid = Grid::new(SIZE, SIZE, |_, _| S::all());
+	let payload = panic::catch_unwind(AssertUnwindSafe(|| collapse_racing_seeded(&mut grid, &rule, ATTEMPTS, 0))).unwrap_err();
+	assert_eq!(payload.downcast_ref::<&str>(), Some(&"attempt failed"));
+}
@@ -0,0 +1,49 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+
+const SIZE: isize = 8;
+
+#[test]
+fn test_regenerate_matches_border() {
+	// two colors which can't neighbor themselves only allow checkerboards, so
+	// the region is completely decided by the cells around it
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let mut grid = SquareGrid::new(SIZE, SIZE, |_, _| S::with_states(&[0, 1]));
+	collapse(&mut grid, &rule).unwrap();
+	let before: Vec<_> = grid.coordinate_list().iter().map(|coord| grid[*coord]).collect();
+	
+	let region = grid.rect(2, 2, 4, 4);
+	regenerate(&mut grid, &rule, &region).unwrap();
+	let after: Vec<_> = grid.coordinate_list().iter().map(|coord| grid[*coord]).collect();
+	assert_eq!(before, after);
+}
+
+#[test]
+fn test_regenerate_only_changes_region() {
+	let rule = free_rule(UniformSetCollapseObserver);
+	let mut grid = SquareGrid::new(SIZE, SIZE, |_, _| S::all());
+	collapse(&mut grid, &rule).unwrap();
+	let before: Vec<_> = grid.coordinate_list().iter().map(|coord| grid[*coord]).collect();
+	
+	// the rect is clipped to the grid
+	let region = grid.rect(-2, 3, 5, 10);
+	assert_eq!(region.len(), 3 * 5);
+	let mut rng = StdRng::seed_from_u64(5);
+	let stats = regenerate_with_rng(&mut grid, &rule, &region, &mut rng).unwrap();
+	assert_eq!(stats.observations, region.len());
+	
+	for (coord, state) in grid.coordinate_list().iter().zip(before) {
+		assert_eq!(grid[*coord].entropy(), 0);
+		if !region.contains(coord) {
+			assert_eq!(grid[*coord], state);
+		}
+	}
+}
@@ -0,0 +1,105 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::square_grid::SquareGrid;
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+
+#[test]
+fn test_pin_propagates() {
+	// with two colors, pinning one cell decides the whole checkerboard
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let all = S::with_states(&[0, 1]);
+	let mut grid = SquareGrid::new(8, 8, |_, _| all);
+	let mut collapser = Collapser::new(&mut grid, &rule);
+	collapser.pin((3, 4), &S::state(1)).unwrap();
+	for y in 0..8 {
+		for x in 0..8 {
+			let expected = S::state(((x + y) % 2) as u32);
+			assert_eq!(collapser.space()[(x, y)], expected);
+		}
+	}
+	let stats = collapser.run().unwrap();
+	assert_eq!(stats.observations, 0);
+}
+
+#[test]
+fn test_unsatisfiable_restriction_is_undone() {
+	// every cell of a 2x2 grid with diagonals touches the other three, so once
+	// two cells are given different colors the other two must share the last
+	let rule = coloring_rule(3, &[DIRECTIONS, DIAGONALS].concat());
+	let mut grid = SquareGrid::new(2, 2, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule);
+	collapser.restrict((0, 0), &S::state(0)).unwrap();
+	let before: Vec<_> = collapser.space().coordinate_list().iter().map(|coord| collapser.space()[*coord]).collect();
+	assert_eq!(before, vec![S::state(0), S::with_states(&[1, 2]), S::with_states(&[1, 2]), S::with_states(&[1, 2])]);
+	
+	let contradiction = collapser.restrict((1, 0), &S::with_states(&[0, 1])).unwrap_err();
+	assert_ne!(contradiction.coordinate, (1, 0));
+	let after: Vec<_> = collapser.space().coordinate_list().iter().map(|coord| collapser.space()[*coord]).collect();
+	assert_eq!(before, after);
+	
+	// an unsatisfiable pin of the cell itself is reported at that cell
+	match collapser.pin((1, 1), &S::state(0)) {
+		Err(PinError::Contradiction(contradiction)) => assert_eq!(contradiction.coordinate, (1, 1)),
+		result => panic!("expected a contradiction, got {:?}", result),
+	}
+}
+
+#[test]
+fn test_pin_rejects_non_final_states() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	let mut grid = SquareGrid::new(4, 4, |_, _| S::all());
+	let mut collapser = Collapser::new(&mut grid, &rule);
+	assert_eq!(collapser.pin((1, 1), &S::with_states(&[0, 1])), Err(PinError::NotFinal));
+	assert_eq!(collapser.pin((1, 1), &S::with_states(&[])), Err(PinError::NotFinal));
+	assert!(collapser.space().coordinate_list().iter().all(|coord| collapser.space()[*coord] == S::all()));
+	collapser.pin((1, 1), &S::state(2)).unwrap();
+	assert_eq!(collapser.space()[(1, 1)], S::state(2));
+}
+
+#[test]
+fn test_backtracking_keeps_restrictions() {
+	let rule = four_color_rule();
+	let mut grid = SquareGrid::new(2, 2, |_, _| S4::all());
+	let mut collapser = Collapser::new(&mut grid, &rule).with_backtracking(16);
+	let (first, observed) = observe_first(&mut collapser);
+	let allowed = restrict_others(&mut collapser, first, observed);
+	
+	let stats = collapser.run().unwrap();
+	assert!(stats.backtracks > 0);
+	assert_eq!(collapser.space()[first], S4::state((observed + 3) % 4));
+	// the other three cells have one allowed color each, so all of them
+	let others: Vec<_> = collapser.space().coordinate_list().iter().copied().filter(|coord| *coord != first).collect();
+	assert!(others.iter().all(|coord| collapser.space()[*coord].entropy() == 0));
+	let colors = others.iter().fold(S4::with_states(&[]), |colors, coord| colors | collapser.space()[*coord]);
+	assert_eq!(colors, allowed);
+}
+
+#[derive(Default)]
+struct Reductions(Vec<(isize, isize)>);
+
+impl CollapseListener<S, SquareGrid<S>> for Reductions {
+	fn reduced(&mut self, coord: (isize, isize), _: &S) {
+		self.0.push(coord);
+	}
+}
+
+#[test]
+fn test_restriction_without_change_isnt_reported() {
+	let rule = coloring_rule(2, &DIRECTIONS);
+	let all = S::with_states(&[0, 1]);
+	let mut grid = SquareGrid::new(4, 4, |_, _| all);
+	let mut reductions = Reductions::default();
+	let mut collapser = Collapser::new(&mut grid, &rule).with_listener(&mut reductions);
+	collapser.pin((2, 2), &S::state(0)).unwrap();
+	// these leave the cells as they were
+	collapser.restrict((1, 1), &S::all()).unwrap();
+	collapser.pin((0, 0), &S::state(0)).unwrap();
+	drop(collapser);
+	// the pin decided every cell, reporting each of them once
+	assert_eq!(reductions.0.len(), 16);
+	assert_eq!(reductions.0.iter().filter(|coord| **coord == (0, 0)).count(), 1);
+}
@@ -0,0 +1,57 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+
+const SIZE: isize = 24;
+
+fn initial_state(x: isize, y: isize) -> S {
+	if (x, y) == (0, 0) {
+		S::state(0)
+	} else {
+		S::all()
+	}
+}
+
+#[test]
+fn test_retries_restore_initial_state() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	let mut retried = false;
+	for seed in 0..20 {
+		let mut grid = SquareGrid::new(SIZE, SIZE, initial_state);
+		let stats = collapse_with_retries_seeded(&mut grid, &rule, 100, seed).unwrap();
+		assert!(stats.attempts >= 1);
+		if stats.attempts == 1 {
+			continue;
+		}
+		retried = true;
+		// the successful attempt should match a single run on a fresh grid
+		// with the same seed
+		let mut expected = SquareGrid::new(SIZE, SIZE, initial_state);
+		let last_seed = derive_seed(seed, stats.attempts as u64 - 1);
+		collapse_with_rng(&mut expected, &rule, &mut StdRng::seed_from_u64(last_seed)).unwrap();
+		for coord in grid.coordinate_list().iter() {
+			assert_eq!(grid[*coord], expected[*coord]);
+		}
+	}
+	assert!(retried);
+}
+
+#[test]
+fn test_retries_give_up() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	// the middle of the top row touches all three colors, so it can never be
+	// colored
+	let mut grid = SquareGrid::new(3, 2, |x, y| match (x, y) {
+		(0, 0) => S::state(0),
+		(2, 0) => S::state(1),
+		(1, 1) => S::state(2),
+		_ => S::all(),
+	});
+	assert!(collapse_with_retries(&mut grid, &rule, 3).is_err());
+}
@@ -0,0 +1,83 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+mod common;
+use common::*;
+
+type S = BitsetState<4>;
+type Grid = SquareGrid<S>;
+type Rule = SetCollapseRule<S, Grid, UniformSetCollapseObserver>;
+
+const A: S = S::state(0);
+const B: S = S::state(1);
+const C: S = S::state(2);
+const D: S = S::state(3);
+
+// Tileset with asymmetric rules, from tests/determinism.rs
+fn tile_rule() -> Rule {
+	SetCollapseRuleBuilder::new(UniformSetCollapseObserver)
+		.allow(&A, &[((0, -1), A | B | C), ((-1, 0), A | B | D)])
+		.allow(&B, &[((0, -1), A | C | D), ((-1, 0), B | C)])
+		.allow(&C, &[((0, -1), B | C | D), ((-1, 0), A | C | D)])
+		.allow(&D, &[((0, -1), A | D), ((-1, 0), B | C | D)])
+		.build()
+}
+
+#[test]
+fn test_support_propagator_collapses() {
+	let rule = tile_rule();
+	for seed in 0..10 {
+		let mut grid = Grid::new(16, 16, |_, _| S::all());
+		let result = Collapser::new(&mut grid, &rule)
+			.with_rng(StdRng::seed_from_u64(seed))
+			.with_propagator(SupportPropagator::new(&rule))
+			.run();
+		if result.is_ok() {
+			assert!(cells(&grid).iter().all(|cell| cell.entropy() == 0));
+			assert_consistent(&grid, &rule);
+		}
+	}
+}
+
+#[test]
+fn test_support_propagator_matches_rule() {
+	// without observations, both ways of propagating should remove exactly the
+	// same states
+	let rule = tile_rule();
+	let pins = [((3, 3), A), ((10, 4), C), ((6, 12), D), ((0, 15), B)];
+	let mut by_rule = Grid::new(16, 16, |_, _| S::all());
+	let mut by_support = Grid::new(16, 16, |_, _| S::all());
+	let mut rule_collapser = Collapser::new(&mut by_rule, &rule);
+	let mut support_collapser = Collapser::new(&mut by_support, &rule).with_propagator(SupportPropagator::new(&rule));
+	for (coord, state) in pins {
+		assert_eq!(rule_collapser.pin(coord, &state).is_ok(), support_collapser.pin(coord, &state).is_ok());
+		assert_eq!(cells(rule_collapser.space()), cells(support_collapser.space()));
+	}
+}
+
+#[test]
+fn test_support_propagator_backtracking() {
+	let rule = coloring_rule(3, &DIRECTIONS);
+	for seed in 0..20 {
+		let mut grid = Grid::new(8, 8, |_, _| S::with_states(&[0, 1, 2]));
+		Collapser::new(&mut grid, &rule)
+			.with_rng(StdRng::seed_from_u64(seed))
+			.with_propagator(SupportPropagator::new(&rule))
+			.with_backtracking(10_000)
+			.run()
+			.unwrap();
+		assert_consistent(&grid, &rule);
+	}
+	
+	// with diagonals, a 2x2 block needs four colors
+	let rule = coloring_rule(3, &[DIRECTIONS, DIAGONALS].concat());
+	let mut grid = Grid::new(2, 2, |_, _| S::with_states(&[0, 1, 2]));
+	let result = Collapser::new(&mut grid, &rule)
+		.with_propagator(SupportPropagator::new(&rule))
+		.with_backtracking(1000)
+		.run();
+	assert!(result.is_err());
+}
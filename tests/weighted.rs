@@ -0,0 +1,65 @@
+use kahuna::*;
+use kahuna::bitset_state::BitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+use rand::{SeedableRng, rngs::StdRng};
+
+mod common;
+use common::*;
+
+type S = BitsetState<3>;
+
+const A: S = S::state(0);
+const B: S = S::state(1);
+const C: S = S::state(2);
+
+#[test]
+fn test_shannon_entropy() {
+	let observer = WeightedSetCollapseObserver::new(&[]);
+	let entropy = SetCollapseObserver::weighted_entropy(&observer, &S::all()).unwrap();
+	assert!((entropy - 3f32.ln()).abs() < 1e-5);
+	let entropy = SetCollapseObserver::weighted_entropy(&observer, &A).unwrap();
+	assert!(entropy.abs() < 1e-5);
+	
+	let observer = WeightedSetCollapseObserver::new(&[(A, 100.0), (B, 1.0)]);
+	let skewed = SetCollapseObserver::weighted_entropy(&observer, &(A | B)).unwrap();
+	let even = SetCollapseObserver::weighted_entropy(&observer, &(B | C)).unwrap();
+	assert!(skewed < even);
+}
+
+#[test]
+fn test_entropy_matches_std_ln() {
+	// entropy is worked out without f32::ln, so check it against the formula
+	// using it over a wide range of weights
+	for weights in [[1.0, 1.0, 1.0], [0.001, 2.5, 7.0], [1e-30, 1.0, 1e30], [0.5, 1e6, 3.0]] {
+		let observer = WeightedSetCollapseObserver::new(&[(A, weights[0]), (B, weights[1]), (C, weights[2])]);
+		let entropy = SetCollapseObserver::weighted_entropy(&observer, &S::all()).unwrap();
+		let total: f64 = weights.iter().map(|weight| *weight as f64).sum();
+		let expected = total.ln() - weights.iter().map(|weight| *weight as f64 * (*weight as f64).ln()).sum::<f64>() / total;
+		assert!((entropy as f64 - expected).abs() < 1e-6, "{} != {} for {:?}", entropy, expected, weights);
+	}
+}
+
+#[test]
+fn test_weighted_observation() {
+	let rule = free_rule(WeightedSetCollapseObserver::new(&[(A, 9.0), (B, 1.0), (C, 0.0)]));
+	let mut grid = SquareGrid::new(20, 20, |_, _| S::all());
+	collapse_with_rng(&mut grid, &rule, &mut StdRng::seed_from_u64(3)).unwrap();
+	let coords = grid.coordinate_list();
+	let a_count = coords.iter().filter(|coord| grid[**coord] == A).count();
+	let c_count = coords.iter().filter(|coord| grid[**coord] == C).count();
+	assert!(a_count > 320 && a_count < 390, "{} of 400 cells were A", a_count);
+	assert_eq!(c_count, 0);
+}
+
+#[test]
+fn test_lowest_weighted_entropy_observed_first() {
+	// both cells have two possible states, but the second is much more
+	// certain to become A
+	let rule = free_rule(WeightedSetCollapseObserver::new(&[(A, 100.0)]));
+	let mut grid = SquareGrid::new(2, 1, |x, _| if x == 0 { B | C } else { A | C });
+	let mut collapser = Collapser::new(&mut grid, &rule);
+	collapser.step().unwrap();
+	assert_eq!(collapser.space()[(0, 0)].entropy(), 1);
+	assert_eq!(collapser.space()[(1, 0)].entropy(), 0);
+}
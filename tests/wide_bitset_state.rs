@@ -0,0 +1,56 @@
+use kahuna::*;
+use kahuna::wide_bitset_state::WideBitsetState;
+use kahuna::set_rule::*;
+use kahuna::square_grid::SquareGrid;
+
+// enough for a tileset of 35 tiles in 4 rotations
+type S = WideBitsetState<140, 3>;
+
+const LEFT: (isize, isize) = (-1, 0);
+const RIGHT: (isize, isize) = (1, 0);
+
+#[test]
+fn test_states_across_words() {
+	let all = S::all();
+	assert_eq!(all.entropy(), 139);
+	assert!(all.has_any_of(&S::state(139)));
+	assert_eq!(S::state(63) | S::state(64) | S::state(139), S::with_states(&[63, 64, 139]));
+	assert_eq!(S::const_or(&[S::state(0), S::state(70), S::state(130)]), S::with_states(&[0, 70, 130]));
+	
+	let mut state = all;
+	state.clear_states(&S::with_states(&[1, 100]));
+	assert_eq!(state.entropy(), 137);
+	assert_eq!(state ^ all, S::with_states(&[1, 100]));
+	assert_eq!(state & S::with_states(&[1, 2, 100]), S::state(2));
+	
+	let mut finals = Vec::new();
+	S::with_states(&[5, 64, 128]).collect_final_states(&mut finals);
+	assert_eq!(finals, vec![S::state(5), S::state(64), S::state(128)]);
+	
+	state.retain_states(&S::state(1));
+	assert!(state.is_contradiction());
+}
+
+#[test]
+fn test_collapse_many_states() {
+	// each row steps up or down by at most one tile at a time
+	let mut builder = SetCollapseRuleBuilder::new(UniformSetCollapseObserver);
+	for tile in 0..140u32 {
+		let nearby = S::with_states(&[(tile + 139) % 140, tile, (tile + 1) % 140]);
+		builder = builder.allow(&S::state(tile), &[(LEFT, nearby), (RIGHT, nearby)]);
+	}
+	let rule = builder.build();
+	let mut grid = SquareGrid::new(20, 20, |_, _| S::all());
+	collapse(&mut grid, &rule).unwrap();
+	for y in 0..20 {
+		for x in 0..19 {
+			assert_eq!(grid[(x, y)].entropy(), 0);
+			assert!(grid[(x, y)].has_any_of(&rule_neighbors(grid[(x + 1, y)])));
+		}
+	}
+}
+
+fn rule_neighbors(state: S) -> S {
+	let tile = (0..140).find(|tile| state == S::state(*tile)).unwrap();
+	S::with_states(&[(tile + 139) % 140, tile, (tile + 1) % 140])
+}